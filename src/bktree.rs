@@ -0,0 +1,132 @@
+//! A BK-tree: an index over a discrete metric space (e.g. the Hamming
+//! distance between perceptual hashes) supporting efficient "all values
+//! within a given radius" queries, in place of an O(n²) pairwise comparison.
+
+use std::collections::HashMap;
+
+/// A type whose values support a discrete distance metric suitable for
+/// indexing in a [BKTree], i.e. one that obeys the triangle inequality.
+pub trait Metric {
+    fn distance(&self, other: &Self) -> u32;
+}
+
+struct Node<T> {
+    value: T,
+    // Children indexed by their distance to this node's value.
+    children: HashMap<u32, Node<T>>
+}
+
+/// A BK-tree over values of type `T`.
+pub struct BKTree<T: Metric> {
+    root: Option<Node<T>>
+}
+
+impl<T: Metric> BKTree<T> {
+    pub fn new() -> Self
+    {
+        BKTree { root: None }
+    }
+
+    /// Insert `value` into the tree.
+    pub fn insert(&mut self, value: T)
+    {
+        match &mut self.root {
+            None => self.root = Some(Node { value, children: HashMap::new() }),
+            Some(root) => Self::insert_into(root, value)
+        }
+    }
+
+    fn insert_into(node: &mut Node<T>, value: T)
+    {
+        let d = node.value.distance(&value);
+        match node.children.get_mut(&d) {
+            Some(child) => Self::insert_into(child, value),
+            None => {
+                node.children.insert(d, Node { value, children: HashMap::new() });
+            }
+        }
+    }
+
+    /// Return every value stored in the tree within `radius` of `target`.
+    pub fn find_within(&self, target: &T, radius: u32) -> Vec<&T>
+    {
+        let mut result = Vec::new();
+        if let Some(root) = &self.root {
+            Self::collect_within(root, target, radius, &mut result);
+        }
+        result
+    }
+
+    fn collect_within<'a>(node: &'a Node<T>, target: &T, radius: u32,
+        result: &mut Vec<&'a T>)
+    {
+        let d = node.value.distance(target);
+        if d <= radius {
+            result.push(&node.value);
+        }
+        // The triangle inequality guarantees that any match lies in a child
+        // edge within [d - radius, d + radius].
+        let lo = d.saturating_sub(radius);
+        let hi = d.saturating_add(radius);
+        for edge in lo..=hi {
+            if let Some(child) = node.children.get(&edge) {
+                Self::collect_within(child, target, radius, result);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl Metric for i32 {
+        fn distance(&self, other: &Self) -> u32
+        {
+            (self - other).unsigned_abs()
+        }
+    }
+
+    #[test]
+    fn find_within_empty_tree_returns_nothing()
+    {
+        let tree: BKTree<i32> = BKTree::new();
+        assert!(tree.find_within(&0, 100).is_empty());
+    }
+
+    #[test]
+    fn find_within_includes_the_exact_radius_boundary()
+    {
+        let mut tree = BKTree::new();
+        tree.insert(0);
+        tree.insert(5);
+        let mut found: Vec<i32> = tree.find_within(&0, 5).into_iter().copied().collect();
+        found.sort();
+        assert_eq!(found, vec![0, 5]);
+    }
+
+    #[test]
+    fn find_within_excludes_values_one_past_the_radius()
+    {
+        let mut tree = BKTree::new();
+        tree.insert(0);
+        tree.insert(6);
+        let found: Vec<i32> = tree.find_within(&0, 5).into_iter().copied().collect();
+        assert_eq!(found, vec![0]);
+    }
+
+    #[test]
+    fn find_within_traverses_every_matching_branch()
+    {
+        // Insertion order determines which edges values land on; cover a
+        // tree with several children off the root to exercise the
+        // [d - radius, d + radius] edge range in collect_within.
+        let mut tree = BKTree::new();
+        for v in [50, 0, 100, 45, 55, 48] {
+            tree.insert(v);
+        }
+        let mut found: Vec<i32> = tree.find_within(&50, 5).into_iter().copied().collect();
+        found.sort();
+        assert_eq!(found, vec![45, 48, 50, 55]);
+    }
+}
@@ -3,14 +3,68 @@
 
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::BufReader;
-use std::path::PathBuf;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use dirs;
+use serde::{Deserialize, Serialize};
 
 /// The name of the cache file.
 const CACHE_NAME: &str = "duplicate-finder_cache.json";
 
+/// The on-disk cache format version. Bumped whenever [CacheEntry] or the
+/// surrounding file shape changes incompatibly, so an old cache is discarded
+/// and rebuilt from scratch rather than misparsed or partially trusted.
+const CACHE_VERSION: u32 = 1;
+
+/// The environment variable read for the cache entry TTL, in seconds. See
+/// [prune].
+const CACHE_TTL_ENV: &str = "DUPLICATE_FINDER_CACHE_TTL";
+/// The default cache entry TTL, used when [CACHE_TTL_ENV] is unset or
+/// unparseable: a week.
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// A cached entry for a single file, keyed by its canonical path in [Cache].
+/// Reused by [`crate::files::File::from()`] in place of recomputing hashes,
+/// as long as `size` and `mtime` still match the file on disk.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub size: u64,
+    pub mtime: u64,
+    /// Hex-encoded MD5 of the first few KB of the file.
+    pub partial_md5: String,
+    /// Hex-encoded MD5 of the whole file, if it was computed.
+    pub full_md5: Option<String>,
+    /// Base64-encoded perceptual hash, if the file is an image or animation.
+    pub ihash: Option<String>,
+    /// Base64-encoded per-frame perceptual hashes, if the file is a video.
+    /// See [`crate::files::VideoHash`].
+    pub video_hash: Option<Vec<String>>,
+    /// A fingerprint of the hasher settings (size, algorithm, resize filter)
+    /// `ihash`/`video_hash` were computed with. [`crate::files::File::from()`]
+    /// only reuses them if this still matches the settings currently in use,
+    /// since hashes produced with different settings are not comparable.
+    pub hash_config: String,
+    /// When this entry was last written or refreshed, in whole seconds since
+    /// the Unix epoch. Used by [prune] to evict entries that have not been
+    /// touched in a while.
+    pub last_access: u64
+}
+
+/// A path-to-[CacheEntry] map, rebuilt from scratch on every [`crate::diff::diff()`]
+/// run so that entries for files that no longer exist are naturally dropped.
+pub type Cache = HashMap<String, CacheEntry>;
+
+/// The on-disk shape of the cache file: a [CACHE_VERSION] alongside the
+/// actual [Cache], so a version mismatch can be detected and the cache
+/// discarded instead of misparsed.
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    entries: Cache
+}
+
 /// The path of the cache file. This path is computed dynamically from
 /// [`dirs::cache_dir()`] and [`CACHE_NAME`].
 pub fn cache_path() -> PathBuf
@@ -21,11 +75,242 @@ pub fn cache_path() -> PathBuf
     path
 }
 
-pub fn load_cache() -> Result<HashMap<String, f32>, String>
+/// The modification time of `metadata`, in whole seconds since the Unix
+/// epoch, or `0` if unavailable.
+pub fn mtime_secs(metadata: &std::fs::Metadata) -> u64
+{
+    metadata.modified().ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// The current time, in whole seconds since the Unix epoch, or `0` if the
+/// system clock is somehow before the epoch. Used to stamp [`CacheEntry::last_access`].
+pub fn now_secs() -> u64
+{
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// The cache entry TTL: [CACHE_TTL_ENV] if set and parseable as a number of
+/// seconds, otherwise [DEFAULT_CACHE_TTL].
+fn cache_ttl() -> Duration
+{
+    std::env::var(CACHE_TTL_ENV).ok()
+        .and_then(|s| s.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_CACHE_TTL)
+}
+
+/// Remove entries whose `last_access` is older than `ttl`, relative to `now`.
+/// Returns the number of entries removed.
+pub fn prune(cache: &mut Cache, ttl: Duration, now: u64) -> usize
+{
+    let ttl_secs = ttl.as_secs();
+    let before = cache.len();
+    cache.retain(|_, entry| now.saturating_sub(entry.last_access) <= ttl_secs);
+    before - cache.len()
+}
+
+/// The [Cache] key for `path`: its canonicalized form, falling back to its
+/// given form if it cannot be canonicalized.
+pub fn canonical_key(path: &Path) -> String
+{
+    path.canonicalize()
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_string_lossy().into_owned())
+}
+
+/// Load the cache file at `path`, discarding it entirely (rather than
+/// attempting to reuse any of its entries) if it was written by a different
+/// [CACHE_VERSION].
+fn load_cache_from(path: &Path) -> Result<Cache, String>
 {
-    let path = cache_path();
     let file = File::open(path).map_err(|e| e.to_string())?;
     let reader = BufReader::new(file);
-    let cache = serde_json::from_reader(reader).map_err(|e| e.to_string())?;
-    Ok(cache)
+    let cache_file: CacheFile = serde_json::from_reader(reader)
+        .map_err(|e| e.to_string())?;
+    if cache_file.version != CACHE_VERSION {
+        return Err(format!("cache format version {} is stale (expected {})",
+            cache_file.version, CACHE_VERSION));
+    }
+    Ok(cache_file.entries)
+}
+
+/// Prune entries older than [cache_ttl()] out of `cache`, then write it to
+/// `path`. If `verbose` is set, print how many entries were evicted.
+fn store_cache_to(path: &Path, cache: &Cache, verbose: bool) -> Result<(), String>
+{
+    let mut cache = cache.clone();
+    let evicted = prune(&mut cache, cache_ttl(), now_secs());
+    if verbose && evicted > 0 {
+        println!("Pruned {} stale cache entr{}", evicted,
+            if evicted == 1 { "y" } else { "ies" });
+    }
+
+    let file = File::create(path).map_err(|e| e.to_string())?;
+    let writer = BufWriter::new(file);
+    let cache_file = CacheFile { version: CACHE_VERSION, entries: cache };
+    serde_json::to_writer(writer, &cache_file).map_err(|e| e.to_string())
+}
+
+/// A writable primary cache layered on top of an ordered list of read-only
+/// fallback caches, e.g. a shared system-wide cache or one shipped alongside
+/// a dataset. [CacheStack::load()] merges every layer, earlier fallbacks
+/// taking precedence over later ones and the primary taking precedence over
+/// all fallbacks; [CacheStack::store()] only ever writes the primary.
+pub struct CacheStack {
+    pub primary: PathBuf,
+    pub fallbacks: Vec<PathBuf>
+}
+
+impl CacheStack {
+    pub fn new(primary: PathBuf, fallbacks: Vec<PathBuf>) -> Self
+    {
+        CacheStack { primary, fallbacks }
+    }
+
+    /// Merge every layer into a single [Cache]. A fallback that is missing,
+    /// unreadable, or of a stale [CACHE_VERSION] is silently skipped rather
+    /// than treated as an error, since it is optional by nature.
+    pub fn load(&self) -> Cache
+    {
+        let mut merged = Cache::new();
+        for path in self.fallbacks.iter().rev() {
+            if let Ok(layer) = load_cache_from(path) {
+                merged.extend(layer);
+            }
+        }
+        if let Ok(primary) = load_cache_from(&self.primary) {
+            merged.extend(primary);
+        }
+        merged
+    }
+
+    /// Write `cache` to the primary layer only; fallbacks are never modified.
+    pub fn store(&self, cache: &Cache, verbose: bool) -> Result<(), String>
+    {
+        store_cache_to(&self.primary, cache, verbose)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache_path(name: &str) -> PathBuf
+    {
+        let mut path = std::env::temp_dir();
+        path.push(format!("duplicate-finder_test_cache_{}_{}", std::process::id(), name));
+        path
+    }
+
+    fn sample_entry() -> CacheEntry
+    {
+        CacheEntry {
+            size: 1,
+            mtime: 1,
+            partial_md5: "0".repeat(32),
+            full_md5: None,
+            ihash: None,
+            video_hash: None,
+            hash_config: "cfg".to_string(),
+            last_access: 1
+        }
+    }
+
+    fn write_cache_file(path: &Path, version: u32, entries: Cache)
+    {
+        let file = File::create(path).unwrap();
+        let writer = BufWriter::new(file);
+        serde_json::to_writer(writer, &CacheFile { version, entries }).unwrap();
+    }
+
+    #[test]
+    fn load_cache_from_rejects_a_stale_version()
+    {
+        let path = temp_cache_path("stale_version");
+        write_cache_file(&path, CACHE_VERSION + 1,
+            Cache::from([("a".to_string(), sample_entry())]));
+        assert!(load_cache_from(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn load_cache_from_accepts_the_current_version()
+    {
+        let path = temp_cache_path("current_version");
+        write_cache_file(&path, CACHE_VERSION,
+            Cache::from([("a".to_string(), sample_entry())]));
+        let loaded = load_cache_from(&path).unwrap();
+        assert_eq!(loaded.len(), 1);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn prune_removes_only_entries_past_the_ttl()
+    {
+        let mut cache = Cache::new();
+        cache.insert("fresh".to_string(),
+            CacheEntry { last_access: 100, ..sample_entry() });
+        cache.insert("stale".to_string(),
+            CacheEntry { last_access: 0, ..sample_entry() });
+        let removed = prune(&mut cache, Duration::from_secs(50), 100);
+        assert_eq!(removed, 1);
+        assert!(cache.contains_key("fresh"));
+        assert!(!cache.contains_key("stale"));
+    }
+
+    #[test]
+    fn prune_keeps_entries_exactly_at_the_ttl_boundary()
+    {
+        let mut cache = Cache::new();
+        cache.insert("boundary".to_string(),
+            CacheEntry { last_access: 50, ..sample_entry() });
+        let removed = prune(&mut cache, Duration::from_secs(50), 100);
+        assert_eq!(removed, 0);
+        assert!(cache.contains_key("boundary"));
+    }
+
+    #[test]
+    fn cache_stack_load_prefers_the_primary_over_fallbacks()
+    {
+        let primary_path = temp_cache_path("stack_primary");
+        let fallback_path = temp_cache_path("stack_fallback");
+        write_cache_file(&primary_path, CACHE_VERSION, Cache::from([
+            ("shared".to_string(), CacheEntry { size: 111, ..sample_entry() })
+        ]));
+        write_cache_file(&fallback_path, CACHE_VERSION, Cache::from([
+            ("shared".to_string(), CacheEntry { size: 222, ..sample_entry() }),
+            ("fallback_only".to_string(), sample_entry())
+        ]));
+        let stack = CacheStack::new(primary_path.clone(), vec![fallback_path.clone()]);
+        let merged = stack.load();
+        assert_eq!(merged.get("shared").unwrap().size, 111);
+        assert!(merged.contains_key("fallback_only"));
+        let _ = std::fs::remove_file(&primary_path);
+        let _ = std::fs::remove_file(&fallback_path);
+    }
+
+    #[test]
+    fn cache_stack_load_prefers_earlier_fallbacks_over_later_ones()
+    {
+        let missing_primary = temp_cache_path("stack_missing_primary");
+        let fallback1 = temp_cache_path("stack_fallback1");
+        let fallback2 = temp_cache_path("stack_fallback2");
+        write_cache_file(&fallback1, CACHE_VERSION, Cache::from([
+            ("shared".to_string(), CacheEntry { size: 1, ..sample_entry() })
+        ]));
+        write_cache_file(&fallback2, CACHE_VERSION, Cache::from([
+            ("shared".to_string(), CacheEntry { size: 2, ..sample_entry() })
+        ]));
+        let stack = CacheStack::new(missing_primary,
+            vec![fallback1.clone(), fallback2.clone()]);
+        let merged = stack.load();
+        assert_eq!(merged.get("shared").unwrap().size, 1);
+        let _ = std::fs::remove_file(&fallback1);
+        let _ = std::fs::remove_file(&fallback2);
+    }
 }
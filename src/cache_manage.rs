@@ -0,0 +1,188 @@
+//! List and selectively clear entries in the on-disk cache, as the `cache`
+//! subcommand. This acts directly on the stored [`cache::CacheEntry`] map,
+//! unlike [`crate::diff::diff()`], which only ever rebuilds it wholesale from
+//! a scan.
+
+use crate::cache::{self, CacheStack};
+
+/// Which field entries are sorted by before `--count` bounds the selection.
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum SortKey {
+    /// Least recently touched first. See [`cache::CacheEntry::last_access`].
+    Oldest,
+    /// Largest file size first.
+    Largest,
+    /// Path, alphabetically.
+    Alpha
+}
+
+fn sort_entries(entries: &mut [(String, cache::CacheEntry)], sort: SortKey)
+{
+    match sort {
+        SortKey::Oldest => entries.sort_by_key(|(_, entry)| entry.last_access),
+        SortKey::Largest => entries.sort_by(|(_, a), (_, b)| b.size.cmp(&a.size)),
+        SortKey::Alpha => entries.sort_by(|(a, _), (b, _)| a.cmp(b))
+    }
+}
+
+/// The first `count` of `entries` (all of them, if `count` is `None`), or
+/// everything but those if `invert` is set.
+fn select(mut entries: Vec<(String, cache::CacheEntry)>, count: Option<usize>,
+    invert: bool) -> Vec<(String, cache::CacheEntry)>
+{
+    let count = count.unwrap_or(entries.len()).min(entries.len());
+    let tail = entries.split_off(count);
+    if invert { tail } else { entries }
+}
+
+/// `secs` seconds as a short human-readable age, e.g. "3d", "4h", "12s".
+fn format_age(secs: u64) -> String
+{
+    if secs >= 86400 {
+        format!("{}d", secs / 86400)
+    } else if secs >= 3600 {
+        format!("{}h", secs / 3600)
+    } else if secs >= 60 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+fn print_table(entries: &[(String, cache::CacheEntry)], now: u64)
+{
+    println!("{:<60} {:<16} {:>12} {:>6}", "PATH", "VALUE", "SIZE", "AGE");
+    for (path, entry) in entries {
+        let value = entry.partial_md5.get(..16).unwrap_or(&entry.partial_md5);
+        println!("{:<60} {:<16} {:>12} {:>6}", path, value, entry.size,
+            format_age(now.saturating_sub(entry.last_access)));
+    }
+}
+
+/// List, or delete, a sorted and bounded selection of cache entries.
+///
+/// Entries are sorted by `sort`, then the first `count` of them are selected
+/// (all of them, if `count` is `None`); `invert` flips the selection to
+/// everything but those. The selection is always printed as a table; if
+/// `delete` is set it is also removed from the cache and the cache is saved
+/// back, unless `dry_run` is set.
+pub fn cache_cmd(sort: SortKey, count: Option<usize>, invert: bool, delete: bool,
+    dry_run: bool)
+{
+    let stack = CacheStack::new(cache::cache_path(), Vec::new());
+    let loaded = stack.load();
+    let now = cache::now_secs();
+
+    let mut entries: Vec<(String, cache::CacheEntry)> = loaded.iter()
+        .map(|(path, entry)| (path.clone(), entry.clone()))
+        .collect();
+    sort_entries(&mut entries, sort);
+    let selected = select(entries, count, invert);
+
+    print_table(&selected, now);
+
+    if !delete {
+        println!("{} entries", selected.len());
+        return;
+    }
+
+    if dry_run {
+        println!("Would delete {} entries", selected.len());
+        return;
+    }
+
+    let mut remaining = loaded;
+    for (path, _) in &selected {
+        remaining.remove(path);
+    }
+    if let Err(e) = stack.store(&remaining, false) {
+        println!("Could not save cache: {}", e);
+        return;
+    }
+    println!("Deleted {} entries", selected.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(size: u64, last_access: u64) -> cache::CacheEntry
+    {
+        cache::CacheEntry {
+            size,
+            mtime: 0,
+            partial_md5: String::new(),
+            full_md5: None,
+            ihash: None,
+            video_hash: None,
+            hash_config: String::new(),
+            last_access
+        }
+    }
+
+    #[test]
+    fn sort_entries_oldest_orders_by_last_access_ascending()
+    {
+        let mut entries = vec![
+            ("b".to_string(), entry(1, 200)),
+            ("a".to_string(), entry(1, 100))
+        ];
+        sort_entries(&mut entries, SortKey::Oldest);
+        assert_eq!(entries[0].0, "a");
+    }
+
+    #[test]
+    fn sort_entries_largest_orders_by_size_descending()
+    {
+        let mut entries = vec![
+            ("small".to_string(), entry(1, 0)),
+            ("large".to_string(), entry(100, 0))
+        ];
+        sort_entries(&mut entries, SortKey::Largest);
+        assert_eq!(entries[0].0, "large");
+    }
+
+    #[test]
+    fn sort_entries_alpha_orders_by_path()
+    {
+        let mut entries = vec![
+            ("b".to_string(), entry(1, 0)),
+            ("a".to_string(), entry(1, 0))
+        ];
+        sort_entries(&mut entries, SortKey::Alpha);
+        assert_eq!(entries[0].0, "a");
+    }
+
+    #[test]
+    fn select_bounds_to_the_given_count()
+    {
+        let entries = vec![
+            ("a".to_string(), entry(1, 0)),
+            ("b".to_string(), entry(1, 0)),
+            ("c".to_string(), entry(1, 0))
+        ];
+        let selected = select(entries, Some(2), false);
+        assert_eq!(selected.len(), 2);
+    }
+
+    #[test]
+    fn select_inverted_returns_everything_past_the_count()
+    {
+        let entries = vec![
+            ("a".to_string(), entry(1, 0)),
+            ("b".to_string(), entry(1, 0)),
+            ("c".to_string(), entry(1, 0))
+        ];
+        let selected = select(entries, Some(1), true);
+        let names: Vec<&str> = selected.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(names, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn select_count_beyond_length_is_clamped()
+    {
+        let entries = vec![("a".to_string(), entry(1, 0))];
+        let selected = select(entries, Some(5), false);
+        assert_eq!(selected.len(), 1);
+    }
+}
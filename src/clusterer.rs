@@ -71,17 +71,24 @@ impl<T: Clone + Eq + Hash> Clusterer<T> {
                 self.sccs.get_mut(&b_index).unwrap().insert(a.clone());
                 self.entries.insert(a.clone(), b_index);
             }
-            (Some(a_index), Some(b_index)) => {
-                // 1. Add all members of b's SCC to a's
-                // 2. Register all members of b's SCC to a's
-                // 3. Delete b's SCC
-                let b_scc = self.sccs.remove(&b_index).unwrap(); // 3.
-                let a_scc = self.sccs.get_mut(&a_index).unwrap();
-                for entry in b_scc {
-                    a_scc.insert(entry.clone()); // 1.
-                    self.entries.insert(entry.clone(), a_index); // 2.
+            (Some(a_index), Some(b_index)) if a_index != b_index => {
+                // Union by size: always drain the smaller SCC into the
+                // larger one, so no element is moved more than O(log n)
+                // times across its lifetime.
+                let (from_index, into_index) =
+                    if self.sccs[&a_index].len() < self.sccs[&b_index].len() {
+                        (a_index, b_index)
+                    } else {
+                        (b_index, a_index)
+                    };
+                let from_scc = self.sccs.remove(&from_index).unwrap();
+                let into_scc = self.sccs.get_mut(&into_index).unwrap();
+                for entry in from_scc {
+                    into_scc.insert(entry.clone());
+                    self.entries.insert(entry, into_index);
                 }
             }
+            (Some(_), Some(_)) => {} // already in the same SCC
         }
     }
 
@@ -91,3 +98,64 @@ impl<T: Clone + Eq + Hash> Clusterer<T> {
         self.sccs.into_values().collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_single_is_idempotent()
+    {
+        let mut c = Clusterer::new();
+        c.add_single(&"a");
+        c.add_single(&"a");
+        let sccs = c.into_sccs();
+        assert_eq!(sccs, vec![HashSet::from(["a"])]);
+    }
+
+    #[test]
+    fn add_link_creates_an_scc_for_two_fresh_entries()
+    {
+        let mut c = Clusterer::new();
+        c.add_link(&"a", &"b");
+        let sccs = c.into_sccs();
+        assert_eq!(sccs, vec![HashSet::from(["a", "b"])]);
+    }
+
+    #[test]
+    fn add_link_keeps_unrelated_sccs_separate()
+    {
+        let mut c = Clusterer::new();
+        c.add_link(&"a", &"b");
+        c.add_link(&"c", &"d");
+        let sccs = c.into_sccs();
+        assert_eq!(sccs.len(), 2);
+        assert!(sccs.contains(&HashSet::from(["a", "b"])));
+        assert!(sccs.contains(&HashSet::from(["c", "d"])));
+    }
+
+    #[test]
+    fn add_link_unions_two_existing_sccs_into_one()
+    {
+        // b and c start in separate SCCs of different sizes, then get linked
+        // directly: this exercises the (Some, Some) union-by-size branch.
+        let mut c = Clusterer::new();
+        c.add_link(&"a", &"b");
+        c.add_link(&"c", &"d");
+        c.add_link(&"e", &"c");
+        c.add_link(&"b", &"c");
+        let sccs = c.into_sccs();
+        assert_eq!(sccs, vec![HashSet::from(["a", "b", "c", "d", "e"])]);
+    }
+
+    #[test]
+    fn add_link_does_nothing_for_entries_already_in_the_same_scc()
+    {
+        let mut c = Clusterer::new();
+        c.add_link(&"a", &"b");
+        c.add_link(&"a", &"b");
+        c.add_link(&"b", &"a");
+        let sccs = c.into_sccs();
+        assert_eq!(sccs, vec![HashSet::from(["a", "b"])]);
+    }
+}
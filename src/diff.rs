@@ -1,4 +1,5 @@
-//use crate::cache;
+use crate::bktree::{BKTree, Metric};
+use crate::cache;
 use crate::clusterer::Clusterer;
 use crate::files;
 use crate::report;
@@ -7,16 +8,247 @@ use std::hash::Hash;
 use std::io;
 use std::io::Write;
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 
 use image_hasher::ImageHash;
-use itertools::Itertools;
 use rayon::prelude::*;
+use serde::Serialize;
 use simple_tqdm::{Tqdm, ParTqdm};
 
+impl Metric for ImageHash {
+    fn distance(&self, other: &Self) -> u32
+    {
+        self.dist(other)
+    }
+}
+
+impl Metric for files::VideoHash {
+    fn distance(&self, other: &Self) -> u32
+    {
+        self.dist(other)
+    }
+}
+
+/// The output format for a [diff] report, in addition to the usual stored
+/// report. The human-readable listing of sets is only printed for
+/// [OutputFormat::Text].
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// The usual human-readable text summary, printed to stdout.
+    Text,
+    /// A pretty-printed JSON document.
+    Json,
+    /// A single-line, compact JSON document.
+    JsonCompact
+}
+
+/// A single file within a [JsonSet], as reported by the `--output json`
+/// [diff] output.
+#[derive(Serialize)]
+struct JsonFile {
+    path: String,
+    md5: String,
+    dimensions: Option<(u32, u32)>,
+    category: String,
+    /// Other paths that are hard links to this same file, excluded from
+    /// comparison by [collapse_hardlinks]. Empty if this file has none.
+    hardlinks: Vec<String>
+}
+
+/// A set of identical or similar files, as reported by the `--output json`
+/// [diff] output.
+#[derive(Serialize)]
+struct JsonSet {
+    /// One of "identical", "same-dims", "diff-dims", "other", "anim",
+    /// "video", or "cluster" for groups of more than two similar files.
+    category: String,
+    files: Vec<JsonFile>
+}
+
+#[derive(Serialize)]
+struct JsonReport {
+    identicals: Vec<JsonSet>,
+    similars: Vec<JsonSet>
+}
+
+fn json_file(file: &files::File,
+    hardlink_aliases: &HashMap<&files::File, Vec<&files::File>>) -> JsonFile
+{
+    JsonFile {
+        path: file.displayname(),
+        md5: hex::encode(file.full_md5()),
+        dimensions: image::image_dimensions(&file.path).ok(),
+        category: match file.category {
+            files::Category::IMAGE => "image",
+            files::Category::ANIMATION => "animation",
+            files::Category::VIDEO => "video",
+            files::Category::UNKNOWN => "unknown"
+        }.to_string(),
+        hardlinks: hardlink_aliases.get(&file)
+            .map(|aliases| aliases.iter().map(|f| f.displayname()).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Categorize a set of similar files the same way [crate::interactive] does
+/// for pairs, falling back to "cluster" for groups of more than two.
+fn json_category(set: &[&files::File]) -> String
+{
+    if set.len() != 2 {
+        return "cluster".to_string();
+    }
+    let (file1, file2) = (set[0], set[1]);
+    match (&file1.category, &file2.category) {
+        (files::Category::ANIMATION, files::Category::ANIMATION) => "anim",
+        (files::Category::VIDEO, files::Category::VIDEO) => "video",
+        (files::Category::IMAGE, files::Category::IMAGE) => {
+            match (image::image_dimensions(&file1.path),
+                   image::image_dimensions(&file2.path)) {
+                (Ok(d1), Ok(d2)) if d1 == d2 => "same-dims",
+                (Ok((w1, h1)), Ok((w2, h2)))
+                    if (w1 < w2 && h1 < h2) || (w2 < w1 && h2 < h1) => "diff-dims",
+                _ => "other"
+            }
+        }
+        _ => "other"
+    }.to_string()
+}
+
+fn to_json_report(identicals: &HashSet<Vec<&files::File>>,
+    similars: &HashSet<Vec<&files::File>>,
+    hardlink_aliases: &HashMap<&files::File, Vec<&files::File>>) -> JsonReport
+{
+    JsonReport {
+        identicals: identicals.iter()
+            .map(|set| JsonSet {
+                category: "identical".to_string(),
+                files: set.iter().map(|f| json_file(f, hardlink_aliases)).collect()
+            })
+            .collect(),
+        similars: similars.iter()
+            .map(|set| JsonSet {
+                category: json_category(set),
+                files: set.iter().map(|f| json_file(f, hardlink_aliases)).collect()
+            })
+            .collect()
+    }
+}
+
+fn write_json_report(json_report: &JsonReport, format: OutputFormat,
+    output_file: Option<PathBuf>) -> Result<(), String>
+{
+    let serialized = match format {
+        OutputFormat::JsonCompact => serde_json::to_string(json_report),
+        _ => serde_json::to_string_pretty(json_report)
+    }.map_err(|e| e.to_string())?;
+    match output_file {
+        Some(path) => std::fs::write(path, serialized).map_err(|e| e.to_string()),
+        None => { println!("{}", serialized); Ok(()) }
+    }
+}
+
 /// The default value for [diff]'s `bits` argument.
 pub const DEFAULT_BITS: usize = 0;
-///// The default value for [diff]'s `parallel` argument.
-//pub const DEFAULT_PARALLEL: usize = 4;
+/// The default value for [diff]'s `hash_size` argument.
+pub const DEFAULT_HASH_SIZE: u32 = 8;
+/// The default value for [diff]'s `hash_alg` argument.
+pub const DEFAULT_HASH_ALG: HashAlg = HashAlg::Gradient;
+/// The default value for [diff]'s `resize_filter` argument.
+pub const DEFAULT_RESIZE_FILTER: ResizeFilter = ResizeFilter::Lanczos3;
+
+/// The perceptual hash algorithm used to hash images, animations, and video
+/// frames, mirroring [image_hasher::HashAlg].
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum HashAlg {
+    Mean,
+    Gradient,
+    DoubleGradient,
+    Blockhash
+}
+
+impl From<HashAlg> for image_hasher::HashAlg {
+    fn from(alg: HashAlg) -> Self
+    {
+        match alg {
+            HashAlg::Mean => image_hasher::HashAlg::Mean,
+            HashAlg::Gradient => image_hasher::HashAlg::Gradient,
+            HashAlg::DoubleGradient => image_hasher::HashAlg::DoubleGradient,
+            HashAlg::Blockhash => image_hasher::HashAlg::Blockhash
+        }
+    }
+}
+
+/// The filter used to resize images down to the hash size before hashing,
+/// mirroring [image_hasher::FilterType].
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum ResizeFilter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3
+}
+
+impl From<ResizeFilter> for image_hasher::FilterType {
+    fn from(filter: ResizeFilter) -> Self
+    {
+        match filter {
+            ResizeFilter::Nearest => image_hasher::FilterType::Nearest,
+            ResizeFilter::Triangle => image_hasher::FilterType::Triangle,
+            ResizeFilter::CatmullRom => image_hasher::FilterType::CatmullRom,
+            ResizeFilter::Gaussian => image_hasher::FilterType::Gaussian,
+            ResizeFilter::Lanczos3 => image_hasher::FilterType::Lanczos3
+        }
+    }
+}
+
+/// A named similarity level, mapped to a bit-distance threshold depending on
+/// the perceptual hash size in use. Ordered from the strictest (fewest
+/// tolerated bit differences) to the loosest.
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Preset {
+    Minimal,
+    VerySmall,
+    Small,
+    Medium,
+    High,
+    VeryHigh
+}
+
+/// Bit-distance thresholds for each [Preset], one row per supported hash
+/// size, columns ordered as [Preset::Minimal] .. [Preset::VeryHigh].
+const PRESET_TABLE: [(u32, [usize;6]);4] = [
+    (8,  [0, 2, 5, 7, 14, 20]),
+    (16, [2, 5, 15, 30, 40, 40]),
+    (32, [4, 10, 20, 40, 40, 40]),
+    (64, [6, 20, 40, 40, 40, 40]),
+];
+
+/// Resolve a named [Preset] into a bit-distance threshold for the given
+/// perceptual hash size.
+///
+/// # Errors
+///
+/// This function errors if `hash_size` is not one of the sizes in
+/// [PRESET_TABLE] (8, 16, 32, or 64).
+fn preset_bits(hash_size: u32, preset: Preset) -> Result<usize, String>
+{
+    PRESET_TABLE.iter()
+        .find(|(size, _)| *size == hash_size)
+        .map(|(_, bits)| bits[preset as usize])
+        .ok_or_else(|| format!(
+            "Unsupported hash size: {}, expected 8, 16, 32, or 64", hash_size))
+}
+
+/// A fingerprint of the resolved hasher settings, stored alongside cached
+/// perceptual hashes so a settings change (`--hash-size`, `--hash-alg`,
+/// `--resize-filter`) invalidates them instead of being silently trusted:
+/// hashes produced with a different size or algorithm are not comparable.
+fn hasher_fingerprint(hash_size: u32, hash_alg: HashAlg, resize_filter: ResizeFilter)
+    -> String
+{
+    format!("{}-{:?}-{:?}", hash_size, hash_alg, resize_filter)
+}
 
 fn make_file_sets<'a, K, F>(files: &HashSet<&'a files::File>, key: F)
     -> HashMap<K, HashSet<&'a files::File>>
@@ -37,45 +269,134 @@ fn make_file_sets<'a, K, F>(files: &HashSet<&'a files::File>, key: F)
     map
 }
 
+/// Collapse files that are hard links to the same inode into a single
+/// representative (the lexicographically first path), so the same physical
+/// content is never compared against itself as a duplicate. The other paths
+/// sharing that inode are returned alongside their representative, keyed by
+/// it, purely for reporting context.
+fn collapse_hardlinks<'a>(files: &HashSet<&'a files::File>)
+    -> (HashSet<&'a files::File>, HashMap<&'a files::File, Vec<&'a files::File>>)
+{
+    let mut by_inode: HashMap<(u64, u64), Vec<&files::File>> = HashMap::new();
+    let mut representatives: HashSet<&files::File> = HashSet::new();
+    for file in files.iter() {
+        match file.inode {
+            Some(inode) => by_inode.entry(inode).or_default().push(file),
+            None => { representatives.insert(file); }
+        }
+    }
+    let mut aliases: HashMap<&files::File, Vec<&files::File>> = HashMap::new();
+    for mut group in by_inode.into_values() {
+        group.sort();
+        let (representative, rest) = group.split_first().unwrap();
+        representatives.insert(representative);
+        if !rest.is_empty() {
+            aliases.insert(*representative, rest.to_vec());
+        }
+    }
+    (representatives, aliases)
+}
+
 /// Find and report duplicate and similar files in the current folder.
 ///
 /// Arguments:
 /// - `bits`: The bit distance in perceptual hashes to consider two images to be
-///   similar. The amount of work grows exponentially with this value; `0` is a
-///   good start. Default: [
-///// - `parallel`: The number of parallel executions to perform the work.
-pub fn diff(bits: Option<usize>/*, parallel: Option<usize>*/) -> ()
+///   similar. Takes precedence over `preset` if both are given. The amount of
+///   work grows exponentially with this value; `0` is a good start. Default:
+///   [DEFAULT_BITS].
+/// - `hash_size`: The width and height in bits of the generated perceptual
+///   hash (8, 16, 32, or 64). Default: [DEFAULT_HASH_SIZE].
+/// - `preset`: A named similarity level, resolved into a `bits` threshold
+///   using `hash_size`. Ignored if `bits` is given.
+/// - `hash_alg`: The perceptual hash algorithm to use. Default: [DEFAULT_HASH_ALG].
+/// - `resize_filter`: The filter used to resize images down to `hash_size`
+///   before hashing. Default: [DEFAULT_RESIZE_FILTER].
+/// - `exclude_dirs`: Directory names or relative path components whose
+///   subtrees are pruned from the scan entirely.
+/// - `include_extensions`: If set, only files whose (case-insensitive)
+///   extension is in this set are scanned.
+/// - `exclude_extensions`: Files whose (case-insensitive) extension is in
+///   this set are never scanned, even if also in `include_extensions`.
+/// - `output`: The output format for the report. Default: [OutputFormat::Text].
+/// - `output_file`: Where to write the `output` report; prints to stdout if
+///   `None`. Ignored when `output` is [OutputFormat::Text].
+/// - `parallel`: The number of threads to use to hash files. Defaults to the
+///   available parallelism.
+/// - `verbose`: If set, print how many stale cache entries were pruned when
+///   the cache is saved. See [`cache::prune`].
+/// - `cache_fallbacks`: Read-only cache layers consulted in addition to the
+///   usual writable cache, earlier entries taking precedence over later
+///   ones. See [`cache::CacheStack`].
+/// - `allow_hard_links`: If set, skip [collapse_hardlinks] so files sharing
+///   an inode can still land in the same `identicals`/`similars` set instead
+///   of being merged into one representative before comparison.
+pub fn diff(bits: Option<usize>, hash_size: Option<u32>, preset: Option<Preset>,
+    hash_alg: Option<HashAlg>, resize_filter: Option<ResizeFilter>,
+    exclude_dirs: Vec<String>, include_extensions: Option<Vec<String>>,
+    exclude_extensions: Vec<String>,
+    output: OutputFormat, output_file: Option<PathBuf>, parallel: Option<usize>,
+    verbose: bool, cache_fallbacks: Vec<PathBuf>, allow_hard_links: bool)
+    -> ()
 {
-    let bits = bits.unwrap_or(DEFAULT_BITS) as u32;
-    //let _parallel = parallel.unwrap_or(DEFAULT_PARALLEL);
+    let hash_size = hash_size.unwrap_or(DEFAULT_HASH_SIZE);
+    let include_extensions: Option<HashSet<String>> = include_extensions
+        .map(|exts| exts.into_iter().map(|e| e.to_lowercase()).collect());
+    let exclude_extensions: HashSet<String> = exclude_extensions.into_iter()
+        .map(|e| e.to_lowercase())
+        .collect();
+    let hash_alg = hash_alg.unwrap_or(DEFAULT_HASH_ALG);
+    let resize_filter = resize_filter.unwrap_or(DEFAULT_RESIZE_FILTER);
+    let hash_config = hasher_fingerprint(hash_size, hash_alg, resize_filter);
+    let hasher = image_hasher::HasherConfig::new()
+        .hash_size(hash_size, hash_size)
+        .hash_alg(hash_alg.into())
+        .resize_filter(resize_filter.into())
+        .to_hasher();
+    let bits = match bits {
+        Some(bits) => bits,
+        None => match preset {
+            Some(preset) => match preset_bits(hash_size, preset) {
+                Ok(bits) => bits,
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            },
+            None => DEFAULT_BITS
+        }
+    } as u32;
+    let parallel = parallel.unwrap_or_else(|| {
+        std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+    });
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(parallel)
+        .build()
+        .expect("Could not build thread pool");
 
     print!("Looking for files... ");
     io::stdout().flush().unwrap();
-    let paths = files::list_files();
+    let paths = files::list_files(&exclude_dirs, &include_extensions,
+        &exclude_extensions);
     println!("found {}", paths.len());
 
-    //print!("Loading cache... ");
-    //io::stdout().flush().unwrap();
-    //let _cache = match cache::load_cache() {
-    //    Ok(cache) => {
-    //        println!("{} entries loaded", cache.len());
-    //        cache
-    //    }
-    //    Err(e) => {
-    //        println!("Could not load cache: {}, continuing with empty cache",
-    //            e);
-    //        HashMap::new()
-    //    }
-    //};
+    print!("Loading cache... ");
+    io::stdout().flush().unwrap();
+    let cache_stack = cache::CacheStack::new(cache::cache_path(), cache_fallbacks);
+    let cache = cache_stack.load();
+    println!("{} entries loaded", cache.len());
 
     let config = simple_tqdm::Config::new()
         .with_desc("Processing files")
         .with_unit("files");
-    let files_result: Result<Vec<files::File>, String> =
+    let files_result: Result<Vec<files::File>, String> = pool.install(|| {
         paths.par_iter()
-        .tqdm_config(config)
-        .map(files::File::from)
-        .collect();
+            .tqdm_config(config)
+            .map(|p| {
+                let cached = cache.get(&cache::canonical_key(p));
+                files::File::from(p, &hasher, &hash_config, cached)
+            })
+            .collect()
+    });
     let files = match files_result {
         Ok(files) => files,
         Err(e) => {
@@ -84,10 +405,48 @@ pub fn diff(bits: Option<usize>/*, parallel: Option<usize>*/) -> ()
         }
     };
     let fileset: HashSet<&files::File> = files.iter().collect();
+    let (fileset, hardlink_aliases): (HashSet<&files::File>,
+        HashMap<&files::File, Vec<&files::File>>) = if allow_hard_links {
+        (fileset, HashMap::new())
+    } else {
+        collapse_hardlinks(&fileset)
+    };
+    if !hardlink_aliases.is_empty() {
+        let alias_count: usize = hardlink_aliases.values().map(|v| v.len()).sum();
+        println!("{} files are hard links to an already-seen file, excluded \
+            from comparison", alias_count);
+    }
 
     print!("Comparing hashes... ");
     io::stdout().flush().unwrap();
-    let hashes = make_file_sets(&fileset, |f| f.md5.clone());
+    // Files can only be identical if they share a size, so group by that
+    // first: files with a size no one else shares can never collide, and are
+    // spared a full read (their `partial_md5`, like every file's, was already
+    // computed from the first few KB). Within a size group, `partial_md5`
+    // (cheap, fixed-size prefix) further narrows down candidates; only files
+    // still colliding on both size and partial hash pay for a full MD5.
+    let mut hashes: HashMap<(u64, [u8;16]), HashSet<&files::File>> = HashMap::new();
+    for (size, size_group) in make_file_sets(&fileset, |f| f.size) {
+        if size_group.len() == 1 {
+            let file = *size_group.iter().next().unwrap();
+            hashes.entry((size, file.partial_md5)).or_default().insert(file);
+            continue;
+        }
+        for (partial_md5, partial_group) in
+            make_file_sets(&size_group, |f| f.partial_md5)
+        {
+            if partial_group.len() == 1 {
+                let file = *partial_group.iter().next().unwrap();
+                hashes.entry((size, partial_md5)).or_default().insert(file);
+                continue;
+            }
+            for (full_md5, full_group) in
+                make_file_sets(&partial_group, |f| f.full_md5())
+            {
+                hashes.entry((size, full_md5)).or_default().extend(full_group);
+            }
+        }
+    }
     if hashes.len() == files.len() {
         println!("all uniques");
     } else {
@@ -117,15 +476,12 @@ pub fn diff(bits: Option<usize>/*, parallel: Option<usize>*/) -> ()
     let config = simple_tqdm::Config::new()
         .with_desc("Comparing image hashes")
         .with_unit("ihash");
-    for ihashvector in ihashes.keys()
-        .combinations(2)
-        .tqdm_config(config)
-    {
-        let ihash1 = ihashvector.get(0).unwrap();
-        let ihash2 = ihashvector.get(1).unwrap();
-        if ihash1.dist(ihash2) <= bits {
-            clusterer.add_link(ihash1, ihash2);
+    let mut tree: BKTree<ImageHash> = BKTree::new();
+    for ihash in ihashes.keys().tqdm_config(config) {
+        for neighbor in tree.find_within(ihash, bits) {
+            clusterer.add_link(ihash, neighbor);
         }
+        tree.insert(ihash.clone());
     }
     let mut close_images: Vec<HashSet<&files::File>> = Vec::new();
     for scc in clusterer.into_sccs() {
@@ -139,6 +495,39 @@ pub fn diff(bits: Option<usize>/*, parallel: Option<usize>*/) -> ()
     }
     let close_images = close_images; // Remove mut
 
+    let videos: HashSet<&files::File> = uniques.iter()
+        .cloned()
+        .filter(|f| f.video_hash.is_some())
+        .collect();
+    println!("{} unique videos", videos.len());
+
+    let video_hashes = make_file_sets(&videos, |f| f.video_hash.clone().unwrap());
+    let mut video_clusterer: Clusterer<files::VideoHash> = Clusterer::new();
+    for video_hash in video_hashes.keys() {
+        video_clusterer.add_single(video_hash);
+    }
+    let config = simple_tqdm::Config::new()
+        .with_desc("Comparing video hashes")
+        .with_unit("video_hash");
+    let mut video_tree: BKTree<files::VideoHash> = BKTree::new();
+    for video_hash in video_hashes.keys().tqdm_config(config) {
+        for neighbor in video_tree.find_within(video_hash, bits) {
+            video_clusterer.add_link(video_hash, neighbor);
+        }
+        video_tree.insert(video_hash.clone());
+    }
+    let mut close_media: Vec<HashSet<&files::File>> = close_images;
+    for scc in video_clusterer.into_sccs() {
+        let mut group: HashSet<&files::File> = HashSet::new();
+        for video_hash in scc {
+            group.extend(video_hashes.get(&video_hash).unwrap())
+        }
+        if 1 < group.len() {
+            close_media.push(group);
+        }
+    }
+    let close_media = close_media; // Remove mut
+
     println!("Compiling results...");
     let identicals : HashSet<Vec<&files::File>> = hashes.values()
         // Iter<&HashSet<&files::File>>
@@ -155,7 +544,7 @@ pub fn diff(bits: Option<usize>/*, parallel: Option<usize>*/) -> ()
         })
         // Iter<Vec<&files::File>>
         .collect();
-    let similars: HashSet<Vec<&files::File>> = close_images.iter()
+    let similars: HashSet<Vec<&files::File>> = close_media.iter()
         // Iter<&HashSet<&files::File>>
         .map(|s| {
             let mut similar_files: Vec<&files::File> = s.iter()
@@ -168,26 +557,66 @@ pub fn diff(bits: Option<usize>/*, parallel: Option<usize>*/) -> ()
         })
         // Iter<Vec<&files::File>>
         .collect();
-    if let Err(e) = report::store_report(&identicals, &similars) {
+    if matches!(output, OutputFormat::Json | OutputFormat::JsonCompact) {
+        let json_report = to_json_report(&identicals, &similars, &hardlink_aliases);
+        if let Err(e) = write_json_report(&json_report, output, output_file) {
+            println!("Could not write JSON report: {}", e);
+        }
+    }
+
+    let report = report::Report::from(&identicals, &similars);
+    if let Err(e) = report::store_report(&report) {
         println!("Could not store report: {}", e);
     } else {
         println!("Report written");
     }
 
-    println!();
-    for identityset in identicals {
-        print!("identical:");
-        for file in identityset {
-            print!(" {}", file.displayname());
-        }
-        println!();
+    // Rebuilt from the files found this run, so entries for paths that no
+    // longer exist are dropped automatically. `full_md5` is only filled in
+    // where it is already known (loaded from the cache, or computed above by
+    // the identical-grouping or the JSON report), never by an extra read here.
+    let now = cache::now_secs();
+    let new_cache: cache::Cache = files.iter()
+        .map(|f| (cache::canonical_key(&f.path), cache::CacheEntry {
+            size: f.size,
+            mtime: f.mtime,
+            partial_md5: hex::encode(f.partial_md5),
+            full_md5: f.full_md5_cached().map(hex::encode),
+            ihash: f.ihash.as_ref().map(|h| h.to_base64()),
+            video_hash: f.video_hash.as_ref()
+                .map(|vh| vh.0.iter().map(|h| h.to_base64()).collect()),
+            hash_config: hash_config.clone(),
+            last_access: now
+        }))
+        .collect();
+    if let Err(e) = cache_stack.store(&new_cache, verbose) {
+        println!("Could not store cache: {}", e);
     }
-    println!();
-    for similarityset in similars {
-        print!("similar:");
-        for file in similarityset {
-            print!(" {}", file.displayname());
+
+    if let OutputFormat::Text = output {
+        println!();
+        for identityset in identicals {
+            print!("identical:");
+            for file in identityset {
+                print!(" {}", file.displayname());
+                if let Some(aliases) = hardlink_aliases.get(&file) {
+                    print!(" (+{} hard link{})", aliases.len(),
+                        if aliases.len() == 1 { "" } else { "s" });
+                }
+            }
+            println!();
         }
         println!();
+        for similarityset in similars {
+            print!("similar:");
+            for file in similarityset {
+                print!(" {}", file.displayname());
+                if let Some(aliases) = hardlink_aliases.get(&file) {
+                    print!(" (+{} hard link{})", aliases.len(),
+                        if aliases.len() == 1 { "" } else { "s" });
+                }
+            }
+            println!();
+        }
     }
 }
@@ -15,38 +15,46 @@ const FP_NAME: &str = "duplicate-finder_false_positives.json";
 
 #[derive(Serialize, Deserialize)]
 struct JSONFalsePositives {
-    /// The sets of files we want to keep.
+    /// The sets of files we want to keep, each as hex-encoded MD5s.
     pub keep: Vec<Vec<String>>,
-    /// The sets of false positives.
+    /// The sets of false positives, each as hex-encoded MD5s.
     pub false_positives: Vec<Vec<String>>
 }
 
 #[derive(Default)]
 pub struct FalsePositives {
-    /// The sets of files we want to keep.
-    pub keep: HashSet<[[u8;16];2]>,
-    /// The sets of false positives.
-    pub false_positives: HashSet<[[u8;16];2]>
+    /// The sets of files we want to keep, keyed by [md5_set].
+    pub keep: HashSet<Vec<[u8;16]>>,
+    /// The sets of false positives, keyed by [md5_set]. A set may have any
+    /// number of entries: a pair from the ordinary review flow, or a whole
+    /// cluster from the group review flow.
+    pub false_positives: HashSet<Vec<[u8;16]>>
 }
 
-fn set_to_vec(hashes: &HashSet<[[u8;16];2]>) -> Vec<Vec<String>>
+/// The canonical key for a set of MD5 hashes: sorted, so membership does not
+/// depend on the order the files were discovered in.
+pub fn md5_set(md5s: &[[u8;16]]) -> Vec<[u8;16]>
+{
+    let mut v = md5s.to_vec();
+    v.sort();
+    v
+}
+
+fn set_to_vec(hashes: &HashSet<Vec<[u8;16]>>) -> Vec<Vec<String>>
 {
     hashes.iter()
-        // Iter<&[[u8;16];2]>
-        .map(|a| {
-            let mut v: Vec<String> = a.iter()
-                // Iter<&[u8;16]>
-                .map(hex::encode)
-                // Iter<String>
-                .collect();
-            v.sort();
-            v
-        })
+        // Iter<&Vec<[u8;16]>>
+        .map(|a| a.iter()
+            // Iter<&[u8;16]>
+            .map(hex::encode)
+            // Iter<String>
+            .collect()
+        )
         // Iter<Vec<String>>
         .collect()
 }
 
-fn vec_to_set(hashes: &Vec<Vec<String>>) -> Result<HashSet<[[u8;16];2]>, String>
+fn vec_to_set(hashes: &Vec<Vec<String>>) -> Result<HashSet<Vec<[u8;16]>>, String>
 {
     hashes.iter()
         // Iter<&Vec<String>>
@@ -70,17 +78,9 @@ fn vec_to_set(hashes: &Vec<Vec<String>>) -> Result<HashSet<[[u8;16];2]>, String>
                 // Iter<Result<[u8;16], String>>
                 .collect::<Result<Vec<[u8;16]>, String>>() // stops at first Err
                 // Result<Vec<[u8;16]>, String>
-                .and_then(|v| TryInto::<[[u8;16];2]>::try_into(v)
-                    // Result<[[u8;16];2], Vec<[u8;16]>
-                    .map_err(|v|
-                        format!("Invalid number of entries: {}, expected 2",
-                                v.len())
-                    )
-                    // Result<[[u8;16];2], String>
-                )
-                // Result<[[u8;16];2], String>
+                .map(|v| md5_set(&v))
         })
-        // Iter<Result<[[u8;16];2], String>
+        // Iter<Result<Vec<[u8;16]>, String>
         .collect() // stops at first Err
 }
 
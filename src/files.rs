@@ -1,14 +1,24 @@
 use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::fs;
 use std::hash::{Hash, Hasher};
 use std::io;
+use std::io::Read;
 use std::path::PathBuf;
+use std::sync::OnceLock;
 
 use image::AnimationDecoder;
-use image_hasher::{HasherConfig, ImageHash};
+use image_hasher::{Hasher as ImageHasher, ImageHash};
 use md5::{Digest, Md5};
 use walkdir::WalkDir;
 
+use crate::cache::CacheEntry;
+
+/// The number of leading bytes hashed for [File]'s `partial_md5`, cheap enough
+/// to compute for every file found by [list_files()] without reading their
+/// full contents.
+const PARTIAL_HASH_SIZE: usize = 4096;
+
 /// The category of file in regards to comparison.
 #[derive(PartialEq,Eq)]
 pub enum Category {
@@ -74,12 +84,55 @@ pub fn get_category(path: &PathBuf) -> Result<Category, String>
     Ok(Category::UNKNOWN)
 }
 
-pub fn list_files() -> Vec<PathBuf>
+/// Whether `entry` is a directory whose name or relative path contains one of
+/// `exclude_dirs`, so [list_files] can prune it without descending.
+fn is_excluded_dir(entry: &walkdir::DirEntry, exclude_dirs: &[String]) -> bool
+{
+    entry.file_type().is_dir()
+        && exclude_dirs.iter().any(|pattern|
+            entry.path().components()
+                .any(|c| c.as_os_str() == pattern.as_str())
+        )
+}
+
+/// Whether `path`'s extension (case-insensitive) passes `include_extensions`
+/// (if set, only extensions in it pass) and `exclude_extensions` (extensions
+/// in it never pass).
+fn passes_extension_filter(path: &PathBuf,
+    include_extensions: &Option<HashSet<String>>,
+    exclude_extensions: &HashSet<String>) -> bool
+{
+    let extension = path.extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    if exclude_extensions.contains(&extension) {
+        return false;
+    }
+    match include_extensions {
+        Some(include_extensions) => include_extensions.contains(&extension),
+        None => true
+    }
+}
+
+/// List every file under the current directory, for [crate::diff::diff] to
+/// scan.
+///
+/// Arguments:
+/// - `exclude_dirs`: directory names or relative path components whose
+///   subtrees are pruned from the walk entirely, without being descended
+///   into.
+/// - `include_extensions`: if set, only files whose (case-insensitive)
+///   extension is in this set are returned.
+/// - `exclude_extensions`: files whose (case-insensitive) extension is in
+///   this set are never returned, even if also in `include_extensions`.
+pub fn list_files(exclude_dirs: &[String],
+    include_extensions: &Option<HashSet<String>>,
+    exclude_extensions: &HashSet<String>) -> Vec<PathBuf>
 {
     WalkDir::new(".")
         .into_iter()
-        //.filter_entry(|entry| entry.file_type().is_file())
-        //.map(|entry| entry.into_path())
+        .filter_entry(|entry| !is_excluded_dir(entry, exclude_dirs))
         // Result<DirEntry, Error>
         .filter_map(|result|
             match result {
@@ -91,25 +144,64 @@ pub fn list_files() -> Vec<PathBuf>
                 _ => None
             }
         )
+        .filter(|path| passes_extension_filter(path, include_extensions, exclude_extensions))
         .collect()
 }
 
+/// The device and inode number of a file, used to detect hard links so they
+/// are not reported as distinct duplicates of one another. Always `None` on
+/// non-Unix platforms.
+#[cfg(unix)]
+fn inode_of(metadata: &fs::Metadata) -> Option<(u64, u64)>
+{
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+#[cfg(not(unix))]
+fn inode_of(_metadata: &fs::Metadata) -> Option<(u64, u64)>
+{
+    None
+}
+
 pub struct File {
     pub path: PathBuf,
     pub category: Category,
-    pub md5: [u8;16],
-    pub ihash: Option<ImageHash>
+    pub size: u64,
+    /// Modification time, in whole seconds since the Unix epoch. Used
+    /// together with `size` to validate [CacheEntry] reuse.
+    pub mtime: u64,
+    /// The (device, inode) pair identifying the file on disk, if available.
+    /// Files sharing an `inode` are hard links to the same content and are
+    /// collapsed by [`crate::diff::diff`] before duplicate detection.
+    pub inode: Option<(u64, u64)>,
+    /// MD5 of the first [PARTIAL_HASH_SIZE] bytes of the file (or of the
+    /// whole file, if shorter). Cheap to compute for every file; only files
+    /// that collide on both `size` and `partial_md5` need [File::full_md5()].
+    pub partial_md5: [u8;16],
+    full_md5: OnceLock<[u8;16]>,
+    pub ihash: Option<ImageHash>,
+    /// The composite multi-frame hash, set instead of `ihash` for
+    /// [Category::VIDEO] files.
+    pub video_hash: Option<VideoHash>
+}
+
+/// Decode a hex-encoded MD5 as stored in a [CacheEntry].
+fn decode_md5(s: &str) -> Result<[u8;16], String>
+{
+    hex::decode(s)
+        .map_err(|e| e.to_string())
+        .and_then(|v| TryInto::<[u8;16]>::try_into(v)
+            .map_err(|v| format!("Invalid hash length: {}, expected 16", v.len())))
 }
 
-fn get_image_hash(path: &PathBuf) -> Result<ImageHash, String>
+fn get_image_hash(path: &PathBuf, hasher: &ImageHasher) -> Result<ImageHash, String>
 {
     let image = image::open(path).map_err(|e| e.to_string())?;
-    let hasher = HasherConfig::new().to_hasher();
     let hash = hasher.hash_image(&image);
     Ok(hash)
 }
 
-fn get_anim_hash(path: &PathBuf) -> Result<ImageHash, String>
+fn get_anim_hash(path: &PathBuf, hasher: &ImageHasher) -> Result<ImageHash, String>
 {
     let extension: String = path.extension()
         // Option<&OsStr>
@@ -145,12 +237,64 @@ fn get_anim_hash(path: &PathBuf) -> Result<ImageHash, String>
                 .map_err(|e| e.to_string())?
         };
 
-    let hasher = HasherConfig::new().to_hasher();
     let hash = hasher.hash_image(first_frame.buffer());
     Ok(hash)
 }
 
-fn get_video_hash(path: &PathBuf) -> Result<ImageHash, String>
+/// Fractions of a video's duration at which frames are sampled for
+/// [get_video_hash]'s composite hash.
+const VIDEO_SAMPLE_POINTS: [f64;5] = [0.1, 0.3, 0.5, 0.7, 0.9];
+
+/// A composite perceptual hash for a video, one [ImageHash] per frame sampled
+/// at each of [VIDEO_SAMPLE_POINTS]. Kept distinct from the single-frame
+/// `ihash` used for images and animations so that two videos merely sharing
+/// an opening frame are not mistaken for duplicates.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct VideoHash(pub Vec<ImageHash>);
+
+impl VideoHash {
+    /// The mean per-sample-point Hamming distance between two [VideoHash]es,
+    /// rounded up so that it obeys the triangle inequality required by
+    /// [`crate::bktree::Metric`].
+    /// Hashes with a differing number of samples (e.g. one video was too
+    /// short to seek into at every sample point) are considered maximally
+    /// distant.
+    pub fn dist(&self, other: &Self) -> u32
+    {
+        if self.0.is_empty() || self.0.len() != other.0.len() {
+            return u32::MAX;
+        }
+        let total: u32 = self.0.iter().zip(other.0.iter())
+            .map(|(a, b)| a.dist(b))
+            .sum();
+        let samples = self.0.len() as u32;
+        (total + samples - 1) / samples
+    }
+}
+
+fn scale_and_hash(decoded_frame: &ffmpeg_next::util::frame::video::Video,
+    scaler: &mut ffmpeg_next::software::scaling::context::Context, hasher: &ImageHasher)
+    -> Result<ImageHash, String>
+{
+    let mut scaled_frame = ffmpeg_next::util::frame::video::Video::empty();
+    scaler.run(decoded_frame, &mut scaled_frame).map_err(|e| e.to_string())?;
+
+    let data : Vec<u8> = Vec::from(scaled_frame.data(0));
+    let width = scaled_frame.width();
+    let height = scaled_frame.height();
+    // The data vector has row-padding to 32 pixels. Don't ask where this is
+    // documented. Don't ask how much time I spent on this either.
+    let padded_width = (width + 31) & !31;
+    let padded_image : image::ImageBuffer<image::Rgb<u8>, Vec<u8>> =
+        image::ImageBuffer::from_raw(padded_width, height, data)
+        .ok_or("Could not convert frame to image")?;
+    let image = image::imageops::crop_imm(&padded_image, 0, 0, width, height)
+        .to_image();
+
+    Ok(hasher.hash_image(&image))
+}
+
+fn get_video_hash(path: &PathBuf, hasher: &ImageHasher) -> Result<VideoHash, String>
 {
     // Adapted from https://github.com/zmwangx/rust-ffmpeg/blob/master/examples/dump-frames.rs
     let mut ictx = ffmpeg_next::format::input(&path)
@@ -158,6 +302,7 @@ fn get_video_hash(path: &PathBuf) -> Result<ImageHash, String>
     let video_stream = ictx.streams().best(ffmpeg_next::media::Type::Video)
         .ok_or("No suitable video stream found")?;
     let video_stream_index = video_stream.index();
+    let duration = video_stream.duration();
 
     let mut decoder = ffmpeg_next::codec::context::Context::from_parameters(
             video_stream.parameters())
@@ -176,73 +321,163 @@ fn get_video_hash(path: &PathBuf) -> Result<ImageHash, String>
             ffmpeg_next::software::scaling::flag::Flags::BILINEAR)
         .map_err(|e| e.to_string())?;
 
-    let mut decoded_frame = ffmpeg_next::util::frame::video::Video::empty();
-    let mut scaled_frame = ffmpeg_next::util::frame::video::Video::empty();
-    for (_, packet) in ictx.packets()
-        .filter(|(s,_)| s.index() == video_stream_index)
-    {
-        decoder.send_packet(&packet).map_err(|e| e.to_string())?;
-        match decoder.receive_frame(&mut decoded_frame) {
-            Ok(()) => break,
-            Err(e) => match e {
-                ffmpeg_next::util::error::Error::Other {
-                    errno: ffmpeg_next::util::error::EAGAIN } => continue,
-                e => return Err(e.to_string()),
+    let mut hashes = Vec::new();
+    for fraction in VIDEO_SAMPLE_POINTS {
+        let target_ts = (duration as f64 * fraction) as i64;
+        if ictx.seek(target_ts, ..target_ts).is_err() {
+            continue;
+        }
+        decoder.flush();
+
+        let mut decoded_frame = ffmpeg_next::util::frame::video::Video::empty();
+        let mut found = false;
+        for (stream, packet) in ictx.packets() {
+            if stream.index() != video_stream_index
+                || decoder.send_packet(&packet).is_err()
+            {
+                continue;
+            }
+            if let Ok(()) = decoder.receive_frame(&mut decoded_frame) {
+                if decoded_frame.pts().unwrap_or(0) >= target_ts {
+                    found = true;
+                    break;
+                }
             }
         }
+        if !found {
+            continue;
+        }
+        if let Ok(hash) = scale_and_hash(&decoded_frame, &mut scaler, hasher) {
+            hashes.push(hash);
+        }
     }
-    scaler.run(&decoded_frame, &mut scaled_frame).map_err(|e| e.to_string())?;
-
-    let data : Vec<u8> = Vec::from(scaled_frame.data(0));
-    let width = scaled_frame.width();
-    let height = scaled_frame.height();
-    // The data vector has row-padding to 32 pixels. Don't ask where this is
-    // documented. Don't ask how much time I spent on this either.
-    let padded_width = (width + 31) & !31;
-    let padded_image : image::ImageBuffer<image::Rgb<u8>, Vec<u8>> =
-        image::ImageBuffer::from_raw(padded_width, height, data)
-        .ok_or("Could not convert frame to image")?;
-    let image = image::imageops::crop_imm(&padded_image, 0, 0, width, height)
-        .to_image();
 
-    let hasher = HasherConfig::new().to_hasher();
-    let hash = hasher.hash_image(&image);
-    Ok(hash)
+    if hashes.is_empty() {
+        return Err("Could not sample any frame".to_string());
+    }
+    Ok(VideoHash(hashes))
 }
 
 impl File {
-    pub fn from(path: &PathBuf) -> Result<Self, String>
+    /// Build a `File` for `path`, reusing `cached` in place of recomputing
+    /// hashes if its `size` and `mtime` still match the file on disk. The
+    /// perceptual hash fields (`ihash`/`video_hash`) are only reused if
+    /// `cached.hash_config` also matches `hash_config`, the fingerprint of
+    /// the hasher settings currently in use; otherwise they are recomputed
+    /// even though the rest of the entry is still valid.
+    pub fn from(path: &PathBuf, hasher: &ImageHasher, hash_config: &str,
+        cached: Option<&CacheEntry>) -> Result<Self, String>
     {
-        let mut file = File::from_noihash(path)?;
+        let mut needs_hash = true;
+        let mut file = match cached {
+            Some(entry) => {
+                let metadata = fs::metadata(path)
+                    .map_err(|s| format!("{}: {}", path.display(), s))?;
+                if entry.size == metadata.len()
+                    && entry.mtime == crate::cache::mtime_secs(&metadata)
+                {
+                    let full_md5 = OnceLock::new();
+                    if let Some(full) = &entry.full_md5 {
+                        full_md5.set(decode_md5(full)?).ok();
+                    }
+                    let (ihash, video_hash) = if entry.hash_config == hash_config {
+                        needs_hash = false;
+                        let ihash = entry.ihash.as_deref()
+                            .map(|s| ImageHash::from_base64(s).map_err(|e| e.to_string()))
+                            .transpose()?;
+                        let video_hash = entry.video_hash.as_ref()
+                            .map(|frames| frames.iter()
+                                .map(|s| ImageHash::from_base64(s).map_err(|e| e.to_string()))
+                                .collect::<Result<Vec<ImageHash>, String>>()
+                                .map(VideoHash)
+                            )
+                            .transpose()?;
+                        (ihash, video_hash)
+                    } else {
+                        (None, None)
+                    };
+                    File {
+                        path: path.to_path_buf(),
+                        category: get_category(path)?,
+                        size: entry.size,
+                        mtime: entry.mtime,
+                        inode: inode_of(&metadata),
+                        partial_md5: decode_md5(&entry.partial_md5)?,
+                        full_md5,
+                        ihash,
+                        video_hash
+                    }
+                } else {
+                    File::from_noihash(path)?
+                }
+            }
+            None => File::from_noihash(path)?
+        };
 
-        file.ihash = match file.category {
-                Category::IMAGE => Some(get_image_hash(path)),
-                Category::ANIMATION => Some(get_anim_hash(path)),
-                Category::VIDEO => Some(get_video_hash(path)),
-                Category::UNKNOWN => None
+        if needs_hash {
+            match file.category {
+                Category::IMAGE => file.ihash = get_image_hash(path, hasher)
+                    .map(Some)
+                    .unwrap_or_else(|e| { eprintln!("{}: {}", path.display(), e); None }),
+                Category::ANIMATION => file.ihash = get_anim_hash(path, hasher)
+                    .map(Some)
+                    .unwrap_or_else(|e| { eprintln!("{}: {}", path.display(), e); None }),
+                Category::VIDEO => file.video_hash = get_video_hash(path, hasher)
+                    .map(Some)
+                    .unwrap_or_else(|e| { eprintln!("{}: {}", path.display(), e); None }),
+                Category::UNKNOWN => {}
             }
-            // Option<Result<ImageHash, String>>
-            .transpose()
-            // Result<Option<ImageHash>, String>
-            .unwrap_or_else(|e| {
-                eprintln!("{}: {}", path.display(), e);
-                None
-            });
+        }
 
         Ok(file)
     }
     pub fn from_noihash(path: &PathBuf) -> Result<Self, String>
     {
+        let metadata = fs::metadata(path)
+            .map_err(|s| format!("{}: {}", path.display(), s))?;
         let mut file = fs::File::open(path)
             .map_err(|s| format!("{}: {}", path.display(), s))?;
         let mut hasher = Md5::new();
-        let _ = io::copy(&mut file, &mut hasher)
+        let _ = io::copy(&mut (&mut file).take(PARTIAL_HASH_SIZE as u64), &mut hasher)
             .map_err(|s| format!("{}: {}", path.display(), s))?;
         Ok(File {
             path: path.to_path_buf(),
             category: get_category(&path)?,
-            md5: hasher.finalize().into(),
-            ihash: None
+            size: metadata.len(),
+            mtime: crate::cache::mtime_secs(&metadata),
+            inode: inode_of(&metadata),
+            partial_md5: hasher.finalize().into(),
+            full_md5: OnceLock::new(),
+            ihash: None,
+            video_hash: None
+        })
+    }
+
+    /// The full MD5 if it has already been computed by [File::full_md5()],
+    /// without triggering the computation. Used when writing a [CacheEntry]
+    /// so that caching never forces an extra full read.
+    pub fn full_md5_cached(&self) -> Option<[u8;16]>
+    {
+        self.full_md5.get().copied()
+    }
+
+    /// The MD5 of the file's entire contents, computed and cached the first
+    /// time it is needed. Unlike `partial_md5`, this always reads the whole
+    /// file, so it is only worth calling once `size` and `partial_md5` have
+    /// already narrowed down a small set of candidates.
+    pub fn full_md5(&self) -> [u8;16]
+    {
+        *self.full_md5.get_or_init(|| {
+            fs::File::open(&self.path)
+                .and_then(|mut file| {
+                    let mut hasher = Md5::new();
+                    io::copy(&mut file, &mut hasher)?;
+                    Ok(hasher.finalize().into())
+                })
+                .unwrap_or_else(|e| {
+                    eprintln!("{}: {}", self.path.display(), e);
+                    [0; 16]
+                })
         })
     }
 
@@ -282,3 +517,125 @@ impl Hash for File {
         self.path.hash(state);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_temp_file(name: &str, content: &[u8]) -> PathBuf
+    {
+        let mut path = std::env::temp_dir();
+        path.push(format!("duplicate-finder_test_{}_{}", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn partial_md5_only_hashes_the_first_partial_hash_size_bytes()
+    {
+        // The tail, past PARTIAL_HASH_SIZE, is set to a value that would
+        // change the hash if it were included.
+        let mut content = vec![0u8; PARTIAL_HASH_SIZE + 100];
+        for b in content[PARTIAL_HASH_SIZE..].iter_mut() { *b = 0xff; }
+        let path = write_temp_file("partial_md5", &content);
+        let file = File::from_noihash(&path).unwrap();
+        let mut hasher = Md5::new();
+        hasher.update(&content[..PARTIAL_HASH_SIZE]);
+        let expected: [u8;16] = hasher.finalize().into();
+        assert_eq!(file.partial_md5, expected);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn full_md5_is_only_computed_once_requested()
+    {
+        let path = write_temp_file("full_md5_lazy", b"hello world");
+        let file = File::from_noihash(&path).unwrap();
+        assert!(file.full_md5_cached().is_none());
+        let full = file.full_md5();
+        assert_eq!(file.full_md5_cached(), Some(full));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn from_falls_back_to_recompute_when_cached_size_is_stale()
+    {
+        let path = write_temp_file("stale_size", b"actual content");
+        let metadata = fs::metadata(&path).unwrap();
+        let mtime = crate::cache::mtime_secs(&metadata);
+        let stale_entry = CacheEntry {
+            size: metadata.len() + 1, // deliberately wrong, to force a recompute
+            mtime,
+            partial_md5: hex::encode([0u8;16]), // deliberately wrong
+            full_md5: None,
+            ihash: None,
+            video_hash: None,
+            hash_config: "irrelevant".to_string(),
+            last_access: 0
+        };
+        let hasher = image_hasher::HasherConfig::new().to_hasher();
+        let file = File::from(&path, &hasher, "irrelevant", Some(&stale_entry)).unwrap();
+        assert_ne!(file.partial_md5, [0u8;16]);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn from_reuses_cached_fields_when_size_and_mtime_match()
+    {
+        let content = b"cached content";
+        let path = write_temp_file("fresh_cache", content);
+        let metadata = fs::metadata(&path).unwrap();
+        let mtime = crate::cache::mtime_secs(&metadata);
+        let mut md5_hasher = Md5::new();
+        md5_hasher.update(content);
+        let full_md5: [u8;16] = md5_hasher.finalize().into();
+        let entry = CacheEntry {
+            size: metadata.len(),
+            mtime,
+            // Content is shorter than PARTIAL_HASH_SIZE, so partial == full.
+            partial_md5: hex::encode(full_md5),
+            full_md5: Some(hex::encode(full_md5)),
+            ihash: None,
+            video_hash: None,
+            hash_config: "cfg".to_string(),
+            last_access: 0
+        };
+        let hasher = image_hasher::HasherConfig::new().to_hasher();
+        let file = File::from(&path, &hasher, "cfg", Some(&entry)).unwrap();
+        assert_eq!(file.partial_md5, full_md5);
+        assert_eq!(file.full_md5_cached(), Some(full_md5));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn passes_extension_filter_allows_everything_with_no_filters()
+    {
+        let path = PathBuf::from("a.png");
+        assert!(passes_extension_filter(&path, &None, &HashSet::new()));
+    }
+
+    #[test]
+    fn passes_extension_filter_rejects_extensions_not_in_include_list()
+    {
+        let include = Some(HashSet::from(["png".to_string()]));
+        let path = PathBuf::from("a.jpg");
+        assert!(!passes_extension_filter(&path, &include, &HashSet::new()));
+    }
+
+    #[test]
+    fn passes_extension_filter_exclude_list_wins_over_include_list()
+    {
+        let include = Some(HashSet::from(["png".to_string()]));
+        let exclude = HashSet::from(["png".to_string()]);
+        let path = PathBuf::from("a.png");
+        assert!(!passes_extension_filter(&path, &include, &exclude));
+    }
+
+    #[test]
+    fn passes_extension_filter_is_case_insensitive()
+    {
+        let include = Some(HashSet::from(["png".to_string()]));
+        let path = PathBuf::from("A.PNG");
+        assert!(passes_extension_filter(&path, &include, &HashSet::new()));
+    }
+}
@@ -13,6 +13,23 @@ use std::io::Write;
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
 
+use serde::Serialize;
+
+/// A machine-readable summary of an [interactive] review, meant for scripting.
+#[derive(Serialize)]
+struct Summary {
+    /// The number of files trashed (or that would be, in dry-run).
+    trashed: usize,
+    /// The number of files explicitly kept by the user.
+    kept: usize,
+    /// The number of pairs and clusters marked as false positives,
+    /// automatically or by the user, during this review.
+    false_positives: usize,
+    /// `false_positives` divided by the number of pairs and clusters
+    /// reviewed.
+    false_positive_rate: f64
+}
+
 /// The name of the trash directory.
 pub const TRASH_NAME: &str = "duplicate-finder_trash";
 
@@ -23,6 +40,26 @@ pub fn trash_path() -> PathBuf
     path
 }
 
+/// Whether two paths refer to the same inode, i.e. are hard links to one
+/// another. Trashing one of a pair of hard links does not reclaim any space,
+/// so such pairs should generally not be treated as duplicates to clean up.
+///
+/// Always returns `false` on non-Unix platforms.
+#[cfg(unix)]
+fn same_inode(a: &PathBuf, b: &PathBuf) -> bool
+{
+    use std::os::unix::fs::MetadataExt;
+    match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(ma), Ok(mb)) => ma.dev() == mb.dev() && ma.ino() == mb.ino(),
+        _ => false
+    }
+}
+#[cfg(not(unix))]
+fn same_inode(_a: &PathBuf, _b: &PathBuf) -> bool
+{
+    false
+}
+
 /// Send a file to the trash.
 ///
 /// In case of error, the reason is printed, and `false` is returned. Otherwise,
@@ -90,6 +127,46 @@ impl Choice {
     }
 }
 
+/// The choice made when reviewing a cluster of more than two similar files.
+enum GroupChoice {
+    /// Keep the report entry untouched.
+    Skip,
+    /// Keep every file in the cluster.
+    KeepAll,
+    /// Keep only the files at the given 1-based indices, trashing the rest.
+    Keep(Vec<usize>),
+    /// Mark the whole cluster as a false positive.
+    FalsePositive,
+}
+
+/// Prompt the user to choose which of the `n` files in a cluster to keep.
+fn make_group_choice(n: usize) -> GroupChoice
+{
+    print!("Keep which file(s)? [1-{}, a=all, f=false positive, default: skip] ", n);
+    io::stdout().flush().unwrap();
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).unwrap();
+    let answer = answer.trim();
+    if answer.is_empty() {
+        return GroupChoice::Skip;
+    }
+    if answer.eq_ignore_ascii_case("f") {
+        return GroupChoice::FalsePositive;
+    }
+    if answer.eq_ignore_ascii_case("a") {
+        return GroupChoice::KeepAll;
+    }
+    let indices: Vec<usize> = answer.split(',')
+        .filter_map(|s| s.trim().parse::<usize>().ok())
+        .filter(|i| 1 <= *i && *i <= n)
+        .collect();
+    if indices.is_empty() {
+        GroupChoice::Skip
+    } else {
+        GroupChoice::Keep(indices)
+    }
+}
+
 fn make_choice(prompt: &str, default: Choice) -> Choice
 {
     print!("{} [", prompt);
@@ -117,8 +194,66 @@ fn make_choice(prompt: &str, default: Choice) -> Choice
     }
 }
 
-pub fn interactive()
+/// The default command used to view pairs and clusters of images.
+pub const DEFAULT_IMAGE_VIEWER: &str = "feh";
+/// The default command used to view pairs of animations.
+pub const DEFAULT_ANIM_VIEWER: &str = "gwenview";
+/// The default command used to view pairs of videos.
+pub const DEFAULT_VIDEO_VIEWER: &str = "vlc";
+
+/// The environment variable read as a fallback for the image viewer.
+const IMAGE_VIEWER_ENV: &str = "DUPLICATE_FINDER_IMAGE_VIEWER";
+/// The environment variable read as a fallback for the animation viewer.
+const ANIM_VIEWER_ENV: &str = "DUPLICATE_FINDER_ANIM_VIEWER";
+/// The environment variable read as a fallback for the video viewer.
+const VIDEO_VIEWER_ENV: &str = "DUPLICATE_FINDER_VIDEO_VIEWER";
+
+/// Resolve a viewer command: the CLI-provided value if any, otherwise the
+/// `env_var` environment variable if set, otherwise `default`.
+fn resolve_viewer(cli: Option<String>, env_var: &str, default: &str) -> String
+{
+    cli.or_else(|| std::env::var(env_var).ok())
+        .unwrap_or_else(|| default.to_string())
+}
+
+/// Spawn `viewer` with `paths` appended as its final arguments. `viewer` may
+/// include leading fixed arguments, e.g. `"feh --auto-zoom"`.
+fn spawn_viewer(viewer: &str, paths: &[&PathBuf]) -> std::io::Result<std::process::Child>
+{
+    let mut parts = viewer.split_whitespace();
+    let program = parts.next().unwrap_or(viewer);
+    Command::new(program)
+        .args(parts)
+        .args(paths)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+}
+
+/// Either send `path` to the trash, or, if `dry_run` is set, merely print
+/// what would be trashed. Returns `true` in either case unless the actual
+/// trashing failed.
+fn trash_or_log(path: &PathBuf, dry_run: bool) -> bool
+{
+    if dry_run {
+        println!("[dry-run] would delete {}", path.display());
+        true
+    } else {
+        send_to_trash(path)
+    }
+}
+
+pub fn interactive(allow_hard_links: bool, dry_run: bool, json_summary: bool,
+    image_viewer: Option<String>, anim_viewer: Option<String>,
+    video_viewer: Option<String>)
 {
+    let image_viewer = resolve_viewer(image_viewer, IMAGE_VIEWER_ENV,
+        DEFAULT_IMAGE_VIEWER);
+    let anim_viewer = resolve_viewer(anim_viewer, ANIM_VIEWER_ENV,
+        DEFAULT_ANIM_VIEWER);
+    let video_viewer = resolve_viewer(video_viewer, VIDEO_VIEWER_ENV,
+        DEFAULT_VIDEO_VIEWER);
+
     let mut report = match report::load_report() {
         Ok(report) => report,
         Err(e) => {
@@ -138,15 +273,33 @@ pub fn interactive()
     let _ = std::fs::remove_dir_all(&trash);
     std::fs::create_dir(&trash).expect("Could not create trash directory");
 
+    // The ids of pairs that have been handled and can be taken out of the
+    // report.
+    let mut handled: Vec<usize> = Vec::new();
+    // The number of false positives that have been handled automatically from
+    // the false_positives report.
+    let mut fp_auto = 0usize;
+    // The number of pairs/clusters auto-skipped because they already matched
+    // a stored `keep` entry.
+    let mut keep_auto = 0usize;
+    // The number of files actually trashed (or that would be, in dry-run).
+    let mut trashed_count = 0usize;
+    // The number of files explicitly kept by the user.
+    let mut kept_count = 0usize;
+
     let todelete: Vec<PathBuf> = report.identicals.iter()
         // Iter<&Vec<String>>
-        .map(|v| v.iter().skip(1))
-        // Iter<Iter<&String>>
-        .flatten()
-        // Iter<&String>
-        .map(PathBuf::from)
+        .flat_map(|v| {
+            let kept = PathBuf::from(v.get(0).unwrap());
+            v.iter().skip(1)
+                // Iter<&String>
+                .map(PathBuf::from)
+                // Iter<PathBuf>
+                .filter(|f| f.is_file())
+                .filter(|f| allow_hard_links || !same_inode(&kept, f))
+                .collect::<Vec<PathBuf>>()
+        })
         // Iter<PathBuf>
-        .filter(|f| f.is_file())
         .collect();
     if !todelete.is_empty() {
         println!("{} files have identical matches.", todelete.len());
@@ -157,11 +310,14 @@ pub fn interactive()
         answer.make_ascii_lowercase();
         if answer == "\n" || answer == "y\n" {
             for file in todelete {
-                if !send_to_trash(&file) {
+                if !trash_or_log(&file, dry_run) {
                     std::process::exit(1);
                 }
+                trashed_count += 1;
+            }
+            if !dry_run {
+                report.identicals.clear();
             }
-            report.identicals.clear();
         }
     }
 
@@ -179,17 +335,39 @@ pub fn interactive()
     let mut samedims: HashSet<usize> = HashSet::new();
     // The ids of similar images that do not fit the two previous categories.
     let mut other_images: HashSet<usize> = HashSet::new();
-
-    // The ids of pairs that have been handled and can be taken out of the
-    // report.
-    let mut handled: Vec<usize> = Vec::new();
-    // The number of false positives that have been handled automatically from
-    // the false_positives report.
-    let mut fp_auto = 0usize;
+    // Clusters of more than two similar files, by id of appearance in the
+    // report, sorted from largest to smallest file size.
+    let mut groups: HashMap<usize, Vec<files::File>> = HashMap::new();
+    // The ids of clusters of more than two similar files.
+    let mut group_ids: HashSet<usize> = HashSet::new();
 
     for (id, similarityset) in report.similars.iter().enumerate() {
-        // We only consider pairs here
-        if similarityset.len() != 2 {
+        if similarityset.len() < 2 {
+            continue;
+        }
+
+        // Clusters of more than two files are reviewed together, see below.
+        if similarityset.len() > 2 {
+            let mut cluster: Vec<files::File> = similarityset.iter()
+                .map(|p| files::File::from_noihash(&PathBuf::from(p)).unwrap())
+                .collect();
+            let md5set = false_positives::md5_set(
+                &cluster.iter().map(|f| f.full_md5()).collect::<Vec<_>>());
+            if fp.keep.contains(&md5set) {
+                handled.push(id);
+                keep_auto += 1;
+                continue;
+            }
+            if fp.false_positives.contains(&md5set) {
+                handled.push(id);
+                fp_auto += 1;
+                continue;
+            }
+            cluster.sort_by_key(|f|
+                std::fs::metadata(&f.path).map(|m| m.len()).unwrap_or(0));
+            cluster.reverse(); // heaviest first
+            groups.insert(id, cluster);
+            group_ids.insert(id);
             continue;
         }
 
@@ -214,14 +392,21 @@ pub fn interactive()
         let file1 = files::File::from_noihash(&path1).unwrap();
         let file2 = files::File::from_noihash(&path2).unwrap();
 
-        let pair1 = [file1.md5, file2.md5];
-        let pair2 = [file2.md5, file1.md5];
-        if fp.keep.contains(&pair1) || fp.keep.contains(&pair2) {
+        if !allow_hard_links && same_inode(&file1.path, &file2.path) {
+            // Same file under two hard links: trashing one reclaims no
+            // space, treat the pair as already handled.
+            handled.push(id);
+            continue;
+        }
+
+        let md5set = false_positives::md5_set(
+            &[file1.full_md5(), file2.full_md5()]);
+        if fp.keep.contains(&md5set) {
             handled.push(id);
+            keep_auto += 1;
             continue;
         }
-        if fp.false_positives.contains(&pair1)
-            || fp.false_positives.contains(&pair2) {
+        if fp.false_positives.contains(&md5set) {
             handled.push(id);
             fp_auto += 1;
             continue
@@ -289,6 +474,8 @@ pub fn interactive()
     let diffdims = diffdims;
     let samedims = samedims;
     let other_images = other_images;
+    let groups = groups;
+    let group_ids = group_ids;
 
     let mut fp_added = 0usize;
 
@@ -297,30 +484,30 @@ pub fn interactive()
         let (file1, file2) = pairs.get(&id).unwrap();
         println!("\n{}/{}: {} vs {}", progress + 1, diffdims_len,
             file1.path.display(), file2.path.display());
-        let mut viewer = Command::new("feh")
-            .args([&file1.path, &file2.path])
-            .stdin(Stdio::null())
-            .spawn()
-            .expect("Could not start `feh`");
+        let mut viewer = spawn_viewer(&image_viewer, &[&file1.path, &file2.path])
+            .expect(&format!("Could not start viewer `{}`", image_viewer));
         println!("These pictures are similar but of different dimensions.");
         match make_choice("Delete the smaller one?", Choice::Yes) {
             Choice::No => println!("Keeping them in the report"),
             Choice::Yes | Choice::First => {
                 println!("Deleting {}", file1.path.display());
-                if send_to_trash(&file1.path) { handled.push(id); }
+                if trash_or_log(&file1.path, dry_run) { handled.push(id); trashed_count += 1; }
             }
             Choice::Second => {
                 println!("Deleting {}", file2.path.display());
-                if send_to_trash(&file2.path) { handled.push(id); }
+                if trash_or_log(&file2.path, dry_run) { handled.push(id); trashed_count += 1; }
             }
             Choice::KeepBoth => {
                 println!("Keeping both");
-                fp.keep.insert([file1.md5, file2.md5]);
+                fp.keep.insert(false_positives::md5_set(
+                    &[file1.full_md5(), file2.full_md5()]));
                 handled.push(id);
+                kept_count += 2;
             }
             Choice::FalsePositive => {
                 println!("False positive");
-                fp.false_positives.insert([file1.md5, file2.md5]);
+                fp.false_positives.insert(false_positives::md5_set(
+                    &[file1.full_md5(), file2.full_md5()]));
                 handled.push(id);
                 fp_added += 1;
             }
@@ -335,30 +522,30 @@ pub fn interactive()
         let (file1, file2) = pairs.get(&id).unwrap();
         println!("\n{}/{}: {} vs {}", progress + 1, samesize_len,
             file1.path.display(), file2.path.display());
-        let mut viewer = Command::new("feh")
-            .args([&file1.path, &file2.path])
-            .stdin(Stdio::null())
-            .spawn()
-            .expect("Could not start `feh`");
+        let mut viewer = spawn_viewer(&image_viewer, &[&file1.path, &file2.path])
+            .expect(&format!("Could not start viewer `{}`", image_viewer));
         println!("These pictures are similar and have the same dimensions.");
         match make_choice("Delete the heavier one?", Choice::Yes) {
             Choice::No => println!("Keeping them in the report."),
             Choice::First => {
                 println!("Deleting {}", file1.path.display());
-                if send_to_trash(&file1.path) { handled.push(id); }
+                if trash_or_log(&file1.path, dry_run) { handled.push(id); trashed_count += 1; }
             }
             Choice::Yes | Choice::Second => {
                 println!("Deleting {}", file2.path.display());
-                if send_to_trash(&file2.path) { handled.push(id); }
+                if trash_or_log(&file2.path, dry_run) { handled.push(id); trashed_count += 1; }
             }
             Choice::KeepBoth => {
                 println!("Keeping both");
-                fp.keep.insert([file1.md5, file2.md5]);
+                fp.keep.insert(false_positives::md5_set(
+                    &[file1.full_md5(), file2.full_md5()]));
                 handled.push(id);
+                kept_count += 2;
             }
             Choice::FalsePositive => {
                 println!("False positive");
-                fp.false_positives.insert([file1.md5, file2.md5]);
+                fp.false_positives.insert(false_positives::md5_set(
+                    &[file1.full_md5(), file2.full_md5()]));
                 handled.push(id);
                 fp_added += 1;
             }
@@ -373,30 +560,30 @@ pub fn interactive()
         let (file1, file2) = pairs.get(&id).unwrap();
         println!("\n{}/{}: {} vs {}", progress + 1, others_len,
             file1.path.display(), file2.path.display());
-        let mut viewer = Command::new("feh")
-            .args([&file1.path, &file2.path])
-            .stdin(Stdio::null())
-            .spawn()
-            .expect("Could not start `feh`");
+        let mut viewer = spawn_viewer(&image_viewer, &[&file1.path, &file2.path])
+            .expect(&format!("Could not start viewer `{}`", image_viewer));
         println!("These pictures are roughly similar.");
         match make_choice("Keep both?", Choice::Yes) {
             Choice::No => println!("Keeping them in the report."),
             Choice::First => {
                 println!("Deleting {}", file1.path.display());
-                if send_to_trash(&file1.path) { handled.push(id); }
+                if trash_or_log(&file1.path, dry_run) { handled.push(id); trashed_count += 1; }
             }
             Choice::Second => {
                 println!("Deleting {}", file2.path.display());
-                if send_to_trash(&file2.path) { handled.push(id); }
+                if trash_or_log(&file2.path, dry_run) { handled.push(id); trashed_count += 1; }
             }
             Choice::Yes | Choice::KeepBoth => {
                 println!("Keeping both");
-                fp.keep.insert([file1.md5, file2.md5]);
+                fp.keep.insert(false_positives::md5_set(
+                    &[file1.full_md5(), file2.full_md5()]));
                 handled.push(id);
+                kept_count += 2;
             }
             Choice::FalsePositive => {
                 println!("False positive");
-                fp.false_positives.insert([file1.md5, file2.md5]);
+                fp.false_positives.insert(false_positives::md5_set(
+                    &[file1.full_md5(), file2.full_md5()]));
                 handled.push(id);
                 fp_added += 1;
             }
@@ -411,31 +598,30 @@ pub fn interactive()
         let (file1, file2) = pairs.get(&id).unwrap();
         println!("\n{}/{}: {} vs {}", progress + 1, anims_len,
             file1.path.display(), file2.path.display());
-        let mut viewer = Command::new("gwenview")
-            .args([&file1.path, &file2.path])
-            .stdin(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-            .expect("Could not start `gwenview`");
+        let mut viewer = spawn_viewer(&anim_viewer, &[&file1.path, &file2.path])
+            .expect(&format!("Could not start viewer `{}`", anim_viewer));
         println!("These animations start similarly.");
         match make_choice("Keep both?", Choice::Yes) {
             Choice::No => println!("Keeping them in the report."),
             Choice::First => {
                 println!("Deleting {}", file1.path.display());
-                if send_to_trash(&file1.path) { handled.push(id); }
+                if trash_or_log(&file1.path, dry_run) { handled.push(id); trashed_count += 1; }
             }
             Choice::Second => {
                 println!("Deleting {}", file2.path.display());
-                if send_to_trash(&file2.path) { handled.push(id); }
+                if trash_or_log(&file2.path, dry_run) { handled.push(id); trashed_count += 1; }
             }
             Choice::Yes | Choice::KeepBoth => {
                 println!("Keeping both");
-                fp.keep.insert([file1.md5, file2.md5]);
+                fp.keep.insert(false_positives::md5_set(
+                    &[file1.full_md5(), file2.full_md5()]));
                 handled.push(id);
+                kept_count += 2;
             }
             Choice::FalsePositive => {
                 println!("False positive");
-                fp.false_positives.insert([file1.md5, file2.md5]);
+                fp.false_positives.insert(false_positives::md5_set(
+                    &[file1.full_md5(), file2.full_md5()]));
                 handled.push(id);
                 fp_added += 1;
             }
@@ -450,31 +636,30 @@ pub fn interactive()
         let (file1, file2) = pairs.get(&id).unwrap();
         println!("\n{}/{}: {} vs {}", progress + 1, videos_len,
             file1.path.display(), file2.path.display());
-        let mut viewer = Command::new("vlc")
-            .args([&file1.path, &file2.path])
-            .stdin(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-            .expect("Could not start `vlc`");
+        let mut viewer = spawn_viewer(&video_viewer, &[&file1.path, &file2.path])
+            .expect(&format!("Could not start viewer `{}`", video_viewer));
         println!("These videos start similarly.");
         match make_choice("Keep both?", Choice::Yes) {
             Choice::No => println!("Keeping them in the report."),
             Choice::First => {
                 println!("Deleting {}", file1.path.display());
-                if send_to_trash(&file1.path) { handled.push(id); }
+                if trash_or_log(&file1.path, dry_run) { handled.push(id); trashed_count += 1; }
             }
             Choice::Second => {
                 println!("Deleting {}", file2.path.display());
-                if send_to_trash(&file2.path) { handled.push(id); }
+                if trash_or_log(&file2.path, dry_run) { handled.push(id); trashed_count += 1; }
             }
             Choice::Yes | Choice::KeepBoth => {
                 println!("Keeping both");
-                fp.keep.insert([file1.md5, file2.md5]);
+                fp.keep.insert(false_positives::md5_set(
+                    &[file1.full_md5(), file2.full_md5()]));
                 handled.push(id);
+                kept_count += 2;
             }
             Choice::FalsePositive => {
                 println!("False positive");
-                fp.false_positives.insert([file1.md5, file2.md5]);
+                fp.false_positives.insert(false_positives::md5_set(
+                    &[file1.full_md5(), file2.full_md5()]));
                 handled.push(id);
                 fp_added += 1;
             }
@@ -482,22 +667,74 @@ pub fn interactive()
         let _ = viewer.kill();
     }
 
-    println!();
-    handled.sort();
-    handled.reverse();
-    for id in handled {
-        report.similars.swap_remove(id);
-    }
+    println!("\n====================");
 
-    if let Err(e) = report::store_report(&report) {
-        println!("Could not store report: {}", e);
-    } else {
-        println!("Report written");
+    let groups_len = group_ids.len();
+    for (progress, id) in group_ids.into_iter().enumerate() {
+        let cluster = groups.get(&id).unwrap();
+        println!("\n{}/{}: cluster of {} similar files", progress + 1,
+            groups_len, cluster.len());
+        for (i, file) in cluster.iter().enumerate() {
+            let size = std::fs::metadata(&file.path).map(|m| m.len()).unwrap_or(0);
+            println!("  {}. {} ({} bytes)", i + 1, file.path.display(), size);
+        }
+        let paths: Vec<&PathBuf> = cluster.iter().map(|f| &f.path).collect();
+        let mut viewer = spawn_viewer(&image_viewer, &paths)
+            .expect(&format!("Could not start viewer `{}`", image_viewer));
+        println!("These files are part of the same similarity cluster.");
+        match make_group_choice(cluster.len()) {
+            GroupChoice::Skip => println!("Keeping them in the report."),
+            GroupChoice::KeepAll => {
+                println!("Keeping all");
+                handled.push(id);
+                kept_count += cluster.len();
+            }
+            GroupChoice::Keep(indices) => {
+                for (i, file) in cluster.iter().enumerate() {
+                    if !indices.contains(&(i + 1)) {
+                        println!("Deleting {}", file.path.display());
+                        if trash_or_log(&file.path, dry_run) { trashed_count += 1; }
+                    }
+                }
+                kept_count += indices.len();
+                handled.push(id);
+            }
+            GroupChoice::FalsePositive => {
+                println!("False positive");
+                let md5s: Vec<[u8;16]> = cluster.iter()
+                    .map(|f| f.full_md5())
+                    .collect();
+                fp.false_positives.insert(false_positives::md5_set(&md5s));
+                handled.push(id);
+                fp_added += 1;
+            }
+        }
+        let _ = viewer.kill();
     }
-    if let Err(e) = false_positives::store(&fp) {
-        println!("Could not store false positives: {}", e);
+
+    println!();
+    if dry_run {
+        // A dry run must leave the persisted report and false positives
+        // exactly as they were, so the next real run still sees everything
+        // that was only simulated here.
+        println!("Dry run: report and false positive reviews left untouched");
     } else {
-        println!("False positive reviews written");
+        handled.sort();
+        handled.reverse();
+        for id in handled {
+            report.similars.swap_remove(id);
+        }
+
+        if let Err(e) = report::store_report(&report) {
+            println!("Could not store report: {}", e);
+        } else {
+            println!("Report written");
+        }
+        if let Err(e) = false_positives::store(&fp) {
+            println!("Could not store false positives: {}", e);
+        } else {
+            println!("False positive reviews written");
+        }
     }
 
     if !report.similars.is_empty() {
@@ -512,10 +749,30 @@ pub fn interactive()
         }
     }
 
-    if 0 < pairs.len() {
-        let fp_total = fp_auto + fp_added;
+    let fp_total = fp_auto + fp_added;
+    // Pairs and clusters reviewed this run, plus those auto-skipped because
+    // they already matched a stored false_positives entry (fp_auto) or a
+    // stored keep entry (keep_auto): both were already reviewed in a past
+    // run, so both must count towards the rate's denominator, even though
+    // only fp_auto also counts towards fp_total.
+    let reviewed = pairs.len() + groups_len + fp_auto + keep_auto;
+    if 0 < reviewed {
         println!();
         println!("False positives rate: {}% ({}/{})",
-                 100 * fp_total / pairs.len(), fp_total, pairs.len());
+                 100 * fp_total / reviewed, fp_total, reviewed);
+    }
+
+    if json_summary {
+        let summary = Summary {
+            trashed: trashed_count,
+            kept: kept_count,
+            false_positives: fp_total,
+            false_positive_rate: if reviewed > 0 {
+                fp_total as f64 / reviewed as f64
+            } else {
+                0.0
+            }
+        };
+        println!("{}", serde_json::to_string(&summary).unwrap());
     }
 }
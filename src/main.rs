@@ -1,7 +1,13 @@
+pub mod bktree;
 pub mod cache;
+pub mod cache_manage;
 pub mod clusterer;
 pub mod diff;
+pub mod false_positives;
 pub mod files;
+pub mod interactive;
+pub mod report;
+pub mod resolve;
 
 use clap::{Args, Parser, Subcommand};
 
@@ -19,16 +25,132 @@ enum Mode {
     /// Removes entries in the cache that do not reference a file of the current
     /// folder
     Clean,
+    /// List or selectively delete entries in the on-disk cache
+    Cache(CacheArgs),
     /// Review the reported results interactively
-    Interactive
+    Interactive(InteractiveArgs),
+    /// Apply a keep-policy and an action to the whole report without prompting
+    Resolve(ResolveArgs)
+}
+
+#[derive(Args)]
+struct CacheArgs {
+    /// Which field to sort entries by before --count bounds the selection
+    #[arg(long, value_enum, default_value = "alpha")]
+    sort: cache_manage::SortKey,
+    /// Limit the selection to this many entries after sorting; defaults to
+    /// all of them
+    #[arg(long)]
+    count: Option<usize>,
+    /// Select everything but the --count bound, instead of the bound itself
+    #[arg(long)]
+    invert: bool,
+    /// Delete the selected entries instead of only listing them
+    #[arg(long)]
+    delete: bool,
+    /// Print what would be deleted instead of actually touching the cache
+    #[arg(long)]
+    dry_run: bool
+}
+
+#[derive(Args)]
+struct InteractiveArgs {
+    /// Do not skip files that are hard links to one another when trashing
+    /// duplicates. By default, files sharing an inode are treated as already
+    /// handled since trashing one does not reclaim any space
+    #[arg(long)]
+    allow_hard_links: bool,
+    /// Print what would be deleted instead of actually trashing anything
+    #[arg(long)]
+    dry_run: bool,
+    /// Print a machine-readable JSON summary of the review at the end
+    #[arg(long)]
+    json_summary: bool,
+    /// Command used to view pairs and clusters of images. Defaults to
+    /// $DUPLICATE_FINDER_IMAGE_VIEWER, or "feh" if unset
+    #[arg(long)]
+    image_viewer: Option<String>,
+    /// Command used to view pairs of animations. Defaults to
+    /// $DUPLICATE_FINDER_ANIM_VIEWER, or "gwenview" if unset
+    #[arg(long)]
+    anim_viewer: Option<String>,
+    /// Command used to view pairs of videos. Defaults to
+    /// $DUPLICATE_FINDER_VIDEO_VIEWER, or "vlc" if unset
+    #[arg(long)]
+    video_viewer: Option<String>
 }
 
 #[derive(Args)]
 struct DiffArgs {
-    /// Number of bits difference to have similar perceptual hash
+    /// Number of bits difference to have similar perceptual hash. Overrides
+    /// --preset if both are given
     bits: Option<usize>,
-    ///// Number of parallel executions
-    //parallel: Option<usize>
+    /// Size of the generated perceptual hash: 8, 16, 32, or 64
+    #[arg(long)]
+    hash_size: Option<u32>,
+    /// Named similarity level, mapped to a bits threshold depending on
+    /// --hash-size
+    #[arg(long, value_enum)]
+    preset: Option<diff::Preset>,
+    /// Perceptual hash algorithm used to hash images, animations, and video
+    /// frames
+    #[arg(long, value_enum)]
+    hash_alg: Option<diff::HashAlg>,
+    /// Filter used to resize images down to --hash-size before hashing
+    #[arg(long, value_enum)]
+    resize_filter: Option<diff::ResizeFilter>,
+    /// Directory name or relative path component to prune from the scan
+    /// entirely; may be given multiple times
+    #[arg(long)]
+    exclude_dir: Vec<String>,
+    /// Restrict the scan to files with this (case-insensitive) extension; may
+    /// be given multiple times
+    #[arg(long)]
+    include_extension: Vec<String>,
+    /// Skip files with this (case-insensitive) extension, even if also given
+    /// to --include-extension; may be given multiple times
+    #[arg(long)]
+    exclude_extension: Vec<String>,
+    /// Output format for the report, in addition to the usual stored report;
+    /// the human-readable listing of sets is only printed for "text"
+    #[arg(long, value_enum, default_value = "text")]
+    output: diff::OutputFormat,
+    /// Where to write the --output report; prints to stdout if unset
+    #[arg(long)]
+    output_file: Option<std::path::PathBuf>,
+    /// Number of threads to use to hash files, defaults to the available
+    /// parallelism
+    #[arg(long)]
+    parallel: Option<usize>,
+    /// Print extra detail, such as how many stale cache entries were pruned
+    #[arg(long)]
+    verbose: bool,
+    /// Read-only fallback cache file to consult in addition to the usual
+    /// writable cache; may be given multiple times, earlier ones taking
+    /// precedence over later ones
+    #[arg(long)]
+    cache_fallback: Vec<std::path::PathBuf>,
+    /// Do not merge files that are hard links to one another into a single
+    /// representative before comparison. By default, files sharing an inode
+    /// are collapsed since they are necessarily identical
+    #[arg(long)]
+    allow_hard_links: bool
+}
+
+#[derive(Args)]
+struct ResolveArgs {
+    /// Which file of each duplicate set to keep
+    #[arg(long, value_enum)]
+    keep: resolve::KeepPolicy,
+    /// What to do with the files of each set that --keep did not select
+    #[arg(long, value_enum, default_value = "delete")]
+    action: resolve::Action,
+    /// Directory to move duplicates into, required when --action is "move"
+    #[arg(long)]
+    quarantine_dir: Option<std::path::PathBuf>,
+    /// Print what would be done instead of actually touching anything
+    #[arg(long)]
+    dry_run: bool
 }
 
 #[derive(Parser)]
@@ -65,8 +187,26 @@ fn main()
             err.exit();
         }); // CLI
     match cli.mode {
-        Mode::Diff(args) => diff::diff(args.bits/*, args.parallel*/),
+        Mode::Diff(args) => {
+            let include_extensions = if args.include_extension.is_empty() {
+                None
+            } else {
+                Some(args.include_extension)
+            };
+            diff::diff(args.bits, args.hash_size, args.preset, args.hash_alg,
+                args.resize_filter, args.exclude_dir, include_extensions,
+                args.exclude_extension, args.output, args.output_file,
+                args.parallel, args.verbose, args.cache_fallback,
+                args.allow_hard_links)
+        }
         Mode::Clean => todo!(),
-        Mode::Interactive => todo!()
+        Mode::Cache(args) => cache_manage::cache_cmd(args.sort, args.count,
+            args.invert, args.delete, args.dry_run),
+        Mode::Interactive(args) =>
+            interactive::interactive(args.allow_hard_links, args.dry_run,
+                args.json_summary, args.image_viewer, args.anim_viewer,
+                args.video_viewer),
+        Mode::Resolve(args) => resolve::resolve(args.keep, args.action,
+            args.quarantine_dir, args.dry_run)
     }
 }
@@ -0,0 +1,372 @@
+//! Apply a keep-policy and an [Action] to a whole [`report::Report`] without
+//! prompting, for scripted or unattended use.
+
+use crate::false_positives;
+use crate::interactive;
+use crate::report;
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Which file of a duplicate set to keep; the rest are acted upon (see
+/// [Action]).
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum KeepPolicy {
+    /// Keep the file with the oldest modification time.
+    Oldest,
+    /// Keep the file with the newest modification time.
+    Newest,
+    /// Keep the image with the largest dimensions, falling back to file size
+    /// for files that are not images.
+    LargestDimensions,
+    /// Keep the file with the smallest size on disk.
+    SmallestFileSize,
+    /// Keep the file with the shortest path.
+    ShortestPath,
+    /// Keep the file that sorts first alphabetically.
+    FirstAlphabetically
+}
+
+/// What to do with every file in a duplicate set that [KeepPolicy] did not
+/// select to keep.
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Action {
+    /// Send extras to the trash, same as [`crate::interactive`]'s deletion.
+    Delete,
+    /// Delete extras and replace them with hard links to the kept file, so
+    /// the content stays reachable from every original path at no extra disk
+    /// cost.
+    Hardlink,
+    /// Move extras into a quarantine directory instead of trashing them.
+    Move
+}
+
+fn modified(path: &Path) -> Option<SystemTime>
+{
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn size(path: &Path) -> u64
+{
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+fn dimensions_area(path: &Path) -> u64
+{
+    image::image_dimensions(path)
+        .map(|(w, h)| w as u64 * h as u64)
+        .unwrap_or(0)
+}
+
+/// Pick the index in `paths` to keep according to `policy`.
+fn choose_keeper(paths: &[String], policy: KeepPolicy) -> usize
+{
+    let mut best = 0;
+    for i in 1..paths.len() {
+        let candidate = Path::new(&paths[i]);
+        let current = Path::new(&paths[best]);
+        let keep_candidate = match policy {
+            KeepPolicy::Oldest => modified(candidate) < modified(current),
+            KeepPolicy::Newest => modified(candidate) > modified(current),
+            KeepPolicy::LargestDimensions => {
+                let candidate_area = dimensions_area(candidate);
+                let current_area = dimensions_area(current);
+                if candidate_area != current_area {
+                    candidate_area > current_area
+                } else {
+                    size(candidate) > size(current)
+                }
+            }
+            KeepPolicy::SmallestFileSize => size(candidate) < size(current),
+            KeepPolicy::ShortestPath => paths[i].len() < paths[best].len(),
+            KeepPolicy::FirstAlphabetically => paths[i] < paths[best]
+        };
+        if keep_candidate {
+            best = i;
+        }
+    }
+    best
+}
+
+/// Whether `set` is marked to keep in `fp`, based on the MD5 hashes of its
+/// files.
+fn is_false_positive_keep(set: &[String], fp: &false_positives::FalsePositives)
+    -> bool
+{
+    let md5s: Vec<[u8;16]> = set.iter()
+        .filter_map(|p| crate::files::File::from_noihash(&PathBuf::from(p))
+            .ok()
+            .map(|f| f.full_md5()))
+        .collect();
+    if md5s.len() != set.len() {
+        return false;
+    }
+    fp.keep.contains(&false_positives::md5_set(&md5s))
+}
+
+/// Move `file` into `destination_dir`, keeping its file name.
+///
+/// In case of error, the reason is printed, and `false` is returned. Otherwise,
+/// return `true`.
+fn move_to(file: &Path, destination_dir: &Path) -> bool
+{
+    let name = match file.file_name() {
+        Some(name) => name,
+        None => return false
+    };
+    let destination = destination_dir.join(name);
+    // Assume the destination may be a different mountpoint, cannot rename.
+    if let Err(e) = std::fs::copy(file, &destination) {
+        println!("Could not move {}: {}", file.display(), e);
+        return false;
+    }
+    if let Err(e) = std::fs::remove_file(file) {
+        println!("Could not move {}: {}", file.display(), e);
+        return false;
+    }
+    true
+}
+
+/// Trash `path` (so its content is recoverable if this turns out to be a
+/// mistake), then replace it with a hard link to `keeper`.
+///
+/// In case of error, the reason is printed, and `false` is returned. Otherwise,
+/// return `true`.
+fn hardlink_to(path: &Path, keeper: &Path) -> bool
+{
+    if !interactive::send_to_trash(&path.to_path_buf()) {
+        return false;
+    }
+    if let Err(e) = std::fs::hard_link(keeper, path) {
+        println!("Could not hard link {}: {}", path.display(), e);
+        return false;
+    }
+    true
+}
+
+/// Apply `policy`/`action` to a single duplicate set, returning the number of
+/// files acted on. In `dry_run`, nothing is touched and the would-be action is
+/// printed instead.
+fn resolve_set(set: &[String], policy: KeepPolicy, action: Action,
+    quarantine_dir: Option<&Path>, dry_run: bool, action_name: &str,
+    policy_name: &str) -> usize
+{
+    let keep = choose_keeper(set, policy);
+    let mut acted = 0usize;
+    for (i, path) in set.iter().enumerate() {
+        if i == keep {
+            continue;
+        }
+        if dry_run {
+            println!("Would {} {} (keeping {}, policy: {})",
+                action_name, path, set[keep], policy_name);
+            continue;
+        }
+        let acted_on = match action {
+            Action::Delete => interactive::send_to_trash(&PathBuf::from(path)),
+            Action::Hardlink =>
+                hardlink_to(Path::new(path), Path::new(&set[keep])),
+            Action::Move => move_to(Path::new(path), quarantine_dir.unwrap())
+        };
+        if acted_on {
+            acted += 1;
+        }
+    }
+    acted
+}
+
+/// Apply `policy` and `action` to every duplicate set in the stored report,
+/// acting on every file but the one the policy selects to keep. With
+/// [`Action::Hardlink`], only identical sets are acted on and similar sets
+/// are skipped. Pairs marked
+/// as a kept false positive in [`false_positives`] are left untouched. If
+/// `dry_run` is set, print what would be done instead of touching anything.
+///
+/// `quarantine_dir` is only used, and required, when `action` is
+/// [`Action::Move`].
+pub fn resolve(policy: KeepPolicy, action: Action, quarantine_dir: Option<PathBuf>,
+    dry_run: bool)
+{
+    let mut report = match report::load_report() {
+        Ok(report) => report,
+        Err(e) => {
+            println!("Could not load report: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let fp = false_positives::load().unwrap_or_default();
+
+    if let Action::Move = action {
+        if quarantine_dir.is_none() {
+            println!("--quarantine-dir is required for --action move");
+            std::process::exit(1);
+        }
+    }
+
+    if !dry_run {
+        match action {
+            Action::Delete | Action::Hardlink => {
+                let trash = interactive::trash_path();
+                let _ = std::fs::remove_dir_all(&trash);
+                std::fs::create_dir(&trash)
+                    .expect("Could not create trash directory");
+            }
+            Action::Move => {
+                let dir = quarantine_dir.as_ref().unwrap();
+                std::fs::create_dir_all(dir)
+                    .expect("Could not create quarantine directory");
+            }
+        }
+    }
+
+    let policy_name = match policy {
+        KeepPolicy::Oldest => "oldest",
+        KeepPolicy::Newest => "newest",
+        KeepPolicy::LargestDimensions => "largest dimensions",
+        KeepPolicy::SmallestFileSize => "smallest file size",
+        KeepPolicy::ShortestPath => "shortest path",
+        KeepPolicy::FirstAlphabetically => "first alphabetically"
+    };
+    let action_name = match action {
+        Action::Delete => "delete",
+        Action::Hardlink => "hard link",
+        Action::Move => "move"
+    };
+    let action_past = match action {
+        Action::Delete => "deleted",
+        Action::Hardlink => "hard linked",
+        Action::Move => "moved"
+    };
+
+    // Hardlinking aliases two paths onto the same content, so it is only
+    // sound for `identicals`: `similars` sets are perceptually close but not
+    // byte-identical, and hardlinking them would silently destroy the unique
+    // content of every file but the one kept.
+    let process_similars = match action {
+        Action::Hardlink => {
+            if !report.similars.is_empty() {
+                println!("--action hardlink only applies to identical files; \
+                    skipping {} similar set(s)", report.similars.len());
+            }
+            false
+        }
+        Action::Delete | Action::Move => true
+    };
+
+    let mut acted = 0usize;
+    // The ids of sets actually acted upon, to prune from the stored report
+    // afterwards, mirroring `interactive`'s bookkeeping.
+    let mut handled_identicals: Vec<usize> = Vec::new();
+    let mut handled_similars: Vec<usize> = Vec::new();
+
+    for (i, set) in report.identicals.iter().enumerate() {
+        if set.len() < 2 {
+            continue;
+        }
+        if is_false_positive_keep(set, &fp) {
+            if !dry_run {
+                handled_identicals.push(i);
+            }
+            continue;
+        }
+        acted += resolve_set(set, policy, action, quarantine_dir.as_deref(),
+            dry_run, action_name, policy_name);
+        if !dry_run {
+            handled_identicals.push(i);
+        }
+    }
+    if process_similars {
+        for (i, set) in report.similars.iter().enumerate() {
+            if set.len() < 2 {
+                continue;
+            }
+            if is_false_positive_keep(set, &fp) {
+                if !dry_run {
+                    handled_similars.push(i);
+                }
+                continue;
+            }
+            acted += resolve_set(set, policy, action, quarantine_dir.as_deref(),
+                dry_run, action_name, policy_name);
+            if !dry_run {
+                handled_similars.push(i);
+            }
+        }
+    }
+
+    if dry_run {
+        println!("Dry run: no files were actually {}", action_past);
+        return;
+    }
+    println!("{} files {}", acted, action_past);
+
+    handled_identicals.sort();
+    handled_identicals.reverse();
+    for i in handled_identicals {
+        report.identicals.swap_remove(i);
+    }
+    handled_similars.sort();
+    handled_similars.reverse();
+    for i in handled_similars {
+        report.similars.swap_remove(i);
+    }
+    if let Err(e) = report::store_report(&report) {
+        println!("Could not store report: {}", e);
+    } else {
+        println!("Report updated");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+
+    fn temp_file_with_size(name: &str, size: usize) -> String
+    {
+        let mut path = std::env::temp_dir();
+        path.push(format!("duplicate-finder_test_{}_{}", std::process::id(), name));
+        let mut file = std::fs::File::create(&path).unwrap();
+        file.write_all(&vec![0u8; size]).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn choose_keeper_shortest_path_picks_the_shortest_string()
+    {
+        let paths = vec!["a/bb/ccc.txt".to_string(), "a/b.txt".to_string()];
+        assert_eq!(choose_keeper(&paths, KeepPolicy::ShortestPath), 1);
+    }
+
+    #[test]
+    fn choose_keeper_first_alphabetically_picks_the_earliest_string()
+    {
+        let paths = vec!["banana.txt".to_string(), "apple.txt".to_string()];
+        assert_eq!(choose_keeper(&paths, KeepPolicy::FirstAlphabetically), 1);
+    }
+
+    #[test]
+    fn choose_keeper_smallest_file_size_picks_the_lightest_file()
+    {
+        let large = temp_file_with_size("choose_keeper_large", 1000);
+        let small = temp_file_with_size("choose_keeper_small", 10);
+        let paths = vec![large.clone(), small.clone()];
+        assert_eq!(choose_keeper(&paths, KeepPolicy::SmallestFileSize), 1);
+        let _ = std::fs::remove_file(&large);
+        let _ = std::fs::remove_file(&small);
+    }
+
+    #[test]
+    fn choose_keeper_largest_dimensions_falls_back_to_file_size_for_non_images()
+    {
+        // Neither path is a decodable image, so `dimensions_area` is 0 for
+        // both and the policy falls back to comparing file size.
+        let small = temp_file_with_size("choose_keeper_small_dims", 10);
+        let large = temp_file_with_size("choose_keeper_large_dims", 1000);
+        let paths = vec![small.clone(), large.clone()];
+        assert_eq!(choose_keeper(&paths, KeepPolicy::LargestDimensions), 1);
+        let _ = std::fs::remove_file(&small);
+        let _ = std::fs::remove_file(&large);
+    }
+}